@@ -0,0 +1,303 @@
+use std::fmt;
+
+use sqlx::{Postgres, QueryBuilder};
+
+/// statements which should run in order in one transaction
+/// ex.: `SET LOCAL statement_timeout = '30s'` followed by the query
+pub struct Batch<'a> {
+    pub statements: Vec<QueryBuilder<'a, Postgres>>,
+    /// statements are wrapped in transaction with this level, see `into_statements`
+    pub isolation: Option<IsolationLevel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BatchError {
+    /// timeout is not a number with optional unit ex.: `500`, `30s`, `5min`
+    InvalidTimeout(String),
+    /// planner setting value is out of range or has unknown unit
+    InvalidSetting(String),
+    /// not one of the four standard isolation levels
+    InvalidIsolation(String),
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::InvalidTimeout(timeout) => write!(f, "`{timeout}` is not a valid timeout"),
+            BatchError::InvalidSetting(setting) => write!(f, "`{setting}` is not a valid planner setting"),
+            BatchError::InvalidIsolation(level) => write!(f, "`{level}` is not a valid isolation level"),
+        }
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+/// time units accepted by postgres for time settings
+const TIME_UNITS: [&str; 6] = ["us", "ms", "s", "min", "h", "d"];
+
+/// memory units accepted by postgres for memory settings
+const MEMORY_UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+
+/// upper limit of max_parallel_workers_per_gather
+const MAX_PARALLEL_WORKERS: u32 = 1024;
+
+/// common planner settings for heavy queries
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlannerSetting<'a> {
+    /// `SET LOCAL max_parallel_workers_per_gather = 8`
+    ParallelWorkers(u32),
+    /// `SET LOCAL work_mem = '256MB'`
+    WorkMem(&'a str),
+}
+
+/// standard transaction isolation levels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IsolationLevel {
+    ReadUncommitted,
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl IsolationLevel {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            IsolationLevel::ReadUncommitted => "READ UNCOMMITTED",
+            IsolationLevel::ReadCommitted => "READ COMMITTED",
+            IsolationLevel::RepeatableRead => "REPEATABLE READ",
+            IsolationLevel::Serializable => "SERIALIZABLE",
+        }
+    }
+}
+
+/// case insensitive, words separated by space or underscore ex.: `repeatable read`, `READ_COMMITTED`
+impl TryFrom<&str> for IsolationLevel {
+    type Error = BatchError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let normalized = value.trim().replace('_', " ").to_uppercase();
+
+        [
+            IsolationLevel::ReadUncommitted,
+            IsolationLevel::ReadCommitted,
+            IsolationLevel::RepeatableRead,
+            IsolationLevel::Serializable,
+        ]
+        .into_iter()
+        .find(|level| level.as_sql() == normalized)
+        .ok_or_else(|| BatchError::InvalidIsolation(value.to_string()))
+    }
+}
+
+impl<'a> Batch<'a> {
+    pub fn new() -> Self {
+        Self { statements: Vec::new(), isolation: None }
+    }
+
+    pub fn push(mut self, query: QueryBuilder<'a, Postgres>) -> Self {
+        self.statements.push(query);
+        self
+    }
+
+    /// prepends `SET LOCAL statement_timeout = '30s'` so it applies to all statements of the batch,
+    /// batch is run in transaction then, see `into_statements`
+    /// SET does not accept bound values so timeout is validated and rendered as literal
+    pub fn statement_timeout(self, timeout: &str) -> Result<Self, BatchError> {
+        let digits_len = timeout.chars().take_while(|c| c.is_ascii_digit()).count();
+        let unit = &timeout[digits_len..];
+
+        if digits_len == 0 || !(unit.is_empty() || TIME_UNITS.contains(&unit)) {
+            return Err(BatchError::InvalidTimeout(timeout.to_string()));
+        }
+
+        Ok(self.set_local("statement_timeout", &format!("'{0}'", timeout)))
+    }
+
+    /// prepends `SET LOCAL` of planner setting, values are validated as they are rendered as literals
+    /// batch is run in transaction then, see `into_statements`
+    pub fn planner_setting(self, setting: PlannerSetting) -> Result<Self, BatchError> {
+        match setting {
+            PlannerSetting::ParallelWorkers(workers) => {
+                if workers > MAX_PARALLEL_WORKERS {
+                    return Err(BatchError::InvalidSetting(format!("max_parallel_workers_per_gather = {0}", workers)));
+                }
+
+                Ok(self.set_local("max_parallel_workers_per_gather", &workers.to_string()))
+            },
+            PlannerSetting::WorkMem(memory) => {
+                let digits_len = memory.chars().take_while(|c| c.is_ascii_digit()).count();
+                let unit = &memory[digits_len..];
+
+                if digits_len == 0 || !(unit.is_empty() || MEMORY_UNITS.contains(&unit)) {
+                    return Err(BatchError::InvalidSetting(format!("work_mem = {0}", memory)));
+                }
+
+                Ok(self.set_local("work_mem", &format!("'{0}'", memory)))
+            },
+        }
+    }
+
+    pub fn isolation(mut self, level: IsolationLevel) -> Self {
+        self.isolation = Some(level);
+        self
+    }
+
+    /// statements wrapped in `BEGIN ISOLATION LEVEL ..` and `COMMIT` if isolation is set,
+    /// in `BEGIN` and `COMMIT` if there is a SET LOCAL, which has no effect outside of transaction
+    pub fn into_statements(self) -> Vec<QueryBuilder<'a, Postgres>> {
+        let begin = match self.isolation {
+            Some(level) => format!("BEGIN ISOLATION LEVEL {0}", level.as_sql()),
+            None if self.statements.iter().any(|statement| statement.sql().starts_with("SET LOCAL ")) => String::from("BEGIN"),
+            None => return self.statements,
+        };
+
+        let mut statements = Vec::with_capacity(self.statements.len() + 2);
+        statements.push(QueryBuilder::new(begin));
+        statements.extend(self.statements);
+        statements.push(QueryBuilder::new("COMMIT"));
+
+        statements
+    }
+
+    /// SET LOCAL statements are kept in order before the other statements
+    fn set_local(mut self, name: &str, value: &str) -> Self {
+        let statement = QueryBuilder::new(format!("SET LOCAL {0} = {1}", name, value));
+        let position = self
+            .statements
+            .iter()
+            .take_while(|statement| statement.sql().starts_with("SET LOCAL "))
+            .count();
+
+        self.statements.insert(position, statement);
+        self
+    }
+}
+
+impl<'a> Default for Batch<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BaseQuery, Batch, BatchError, Condition, ConditionBuilder, IsolationLevel, PlannerSetting};
+
+    #[test]
+    fn batch_with_statement_timeout() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM events"), &conditions, None, None, None, None);
+
+        let batch = Batch::new().push(query.build()).statement_timeout("30s").unwrap();
+        let statements: Vec<String> = batch.statements.into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(
+            statements,
+            vec!["SET LOCAL statement_timeout = '30s'", "SELECT *\nFROM events\nWHERE\n    org_id = $1"]
+        );
+    }
+
+    #[test]
+    fn batch_timeout_without_isolation() {
+        let conditions: Vec<Condition> = vec![Condition::eq("org_id", 5)];
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM events"), &conditions, None, None, None, None);
+
+        let batch = Batch::new().push(query.build()).statement_timeout("30s").unwrap();
+        let statements: Vec<String> = batch.into_statements().into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(
+            statements,
+            vec!["BEGIN", "SET LOCAL statement_timeout = '30s'", "SELECT *\nFROM events\nWHERE\n    org_id = $1", "COMMIT"]
+        );
+
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM events"), &conditions, None, None, None, None);
+        let statements: Vec<String> = Batch::new().push(query.build()).into_statements().into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(statements, vec!["SELECT *\nFROM events\nWHERE\n    org_id = $1"]);
+    }
+
+    #[test]
+    fn batch_invalid_timeout() {
+        assert_eq!(
+            Batch::new().statement_timeout("30s'; DROP TABLE x").err(),
+            Some(BatchError::InvalidTimeout("30s'; DROP TABLE x".to_string()))
+        );
+        assert!(Batch::new().statement_timeout("s").is_err());
+        assert!(Batch::new().statement_timeout("1500").is_ok());
+    }
+
+    #[test]
+    fn batch_with_planner_settings() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "year", "=", 2024.into(), None)];
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT region, sum(amount)\nFROM sales"), &conditions, None, None, None, None);
+
+        let batch = Batch::new()
+            .push(query.build())
+            .planner_setting(PlannerSetting::ParallelWorkers(8))
+            .unwrap()
+            .planner_setting(PlannerSetting::WorkMem("256MB"))
+            .unwrap();
+        let statements: Vec<String> = batch.statements.into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(
+            statements,
+            vec![
+                "SET LOCAL max_parallel_workers_per_gather = 8",
+                "SET LOCAL work_mem = '256MB'",
+                "SELECT region, sum(amount)\nFROM sales\nWHERE\n    year = $1",
+            ]
+        );
+        assert!(Batch::new().planner_setting(PlannerSetting::ParallelWorkers(5000)).is_err());
+        assert!(Batch::new().planner_setting(PlannerSetting::WorkMem("256mb")).is_err());
+    }
+
+    #[test]
+    fn batch_planner_setting_without_isolation() {
+        let conditions: Vec<Condition> = vec![Condition::eq("year", 2024)];
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT region, sum(amount)\nFROM sales"), &conditions, None, None, None, None);
+
+        let batch = Batch::new()
+            .push(query.build())
+            .planner_setting(PlannerSetting::ParallelWorkers(8))
+            .unwrap();
+        let statements: Vec<String> = batch.into_statements().into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(
+            statements,
+            vec![
+                "BEGIN",
+                "SET LOCAL max_parallel_workers_per_gather = 8",
+                "SELECT region, sum(amount)\nFROM sales\nWHERE\n    year = $1",
+                "COMMIT",
+            ]
+        );
+    }
+
+    #[test]
+    fn batch_repeatable_read() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "year", "=", 2024.into(), None)];
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT region, sum(amount)\nFROM sales"), &conditions, None, None, None, None);
+
+        let batch = Batch::new()
+            .push(query.build())
+            .statement_timeout("30s")
+            .unwrap()
+            .isolation(IsolationLevel::try_from("repeatable read").unwrap());
+        let statements: Vec<String> = batch.into_statements().into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(
+            statements,
+            vec![
+                "BEGIN ISOLATION LEVEL REPEATABLE READ",
+                "SET LOCAL statement_timeout = '30s'",
+                "SELECT region, sum(amount)\nFROM sales\nWHERE\n    year = $1",
+                "COMMIT",
+            ]
+        );
+        assert_eq!(IsolationLevel::try_from("READ_COMMITTED"), Ok(IsolationLevel::ReadCommitted));
+        assert_eq!(
+            IsolationLevel::try_from("snapshot"),
+            Err(BatchError::InvalidIsolation("snapshot".to_string()))
+        );
+    }
+}
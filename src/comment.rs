@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+
+use crate::is_valid_identifier;
+
+/// renders tag as sql comment ex.: `/* app=svc,endpoint=/users */`
+/// characters which could break the key/value list or close the comment are percent encoded
+pub fn tag_comment(tag: &BTreeMap<&str, &str>) -> String {
+    let pairs: Vec<String> = tag
+        .iter()
+        .map(|(key, value)| format!("{0}={1}", escape_tag(key), escape_tag(value)))
+        .collect();
+
+    format!("/* {0} */", pairs.join(","))
+}
+
+fn escape_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '%' | ',' | '=' | '*' | '\'' => escaped.push_str(&format!("%{0:02X}", c as u32)),
+            c if c.is_control() => escaped.push_str(&format!("%{0:02X}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// pg_hint_plan join order hint, put it at the start of the query
+/// ex.: `/*+ Leading(a b c) */` or strict with join direction `/*+ Leading(((a b) c)) */`
+#[derive(Debug, Clone)]
+pub struct JoinOrderHint<'a> {
+    pub aliases: Vec<&'a str>,
+    /// fixes join direction too, left-deep: first alias is the outer side of the first join
+    pub strict: bool,
+}
+
+impl<'a> JoinOrderHint<'a> {
+    pub fn new(aliases: Vec<&'a str>, strict: bool) -> Self {
+        Self { aliases, strict }
+    }
+
+    /// None if there are less than two aliases or an alias is not a plain identifier
+    pub fn as_comment(&self) -> Option<String> {
+        if self.aliases.len() < 2 || !self.aliases.iter().all(|alias| is_valid_identifier(alias)) {
+            return None;
+        }
+
+        let order = if self.strict {
+            self.aliases[1..]
+                .iter()
+                .fold(self.aliases[0].to_string(), |order, alias| format!("({0} {1})", order, alias))
+        } else {
+            self.aliases.join(" ")
+        };
+
+        Some(format!("/*+ Leading({0}) */", order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use crate::{JoinOrderHint, tag_comment};
+
+    #[test]
+    fn tag_comment_sorted_pairs() {
+        let tag = BTreeMap::from([("endpoint", "/users"), ("app", "svc")]);
+
+        assert_eq!(tag_comment(&tag), "/* app=svc,endpoint=/users */");
+    }
+
+    #[test]
+    fn tag_comment_escaped() {
+        let tag = BTreeMap::from([("route", "a=b,c*/ DROP"), ("user", "o'neil\n")]);
+
+        assert_eq!(tag_comment(&tag), "/* route=a%3Db%2Cc%2A/ DROP,user=o%27neil%0A */");
+    }
+
+    #[test]
+    fn join_order_hint() {
+        let hint = JoinOrderHint::new(vec!["o", "c", "p"], false);
+        let strict_hint = JoinOrderHint::new(vec!["o", "c", "p"], true);
+
+        assert_eq!(hint.as_comment(), Some("/*+ Leading(o c p) */".to_string()));
+        assert_eq!(strict_hint.as_comment(), Some("/*+ Leading(((o c) p)) */".to_string()));
+        assert_eq!(JoinOrderHint::new(vec!["o", "c */ x"], false).as_comment(), None);
+    }
+}
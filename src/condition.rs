@@ -1,7 +1,37 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use chrono::{Duration, NaiveDateTime};
 use serde_json::Value;
 use sqlx::{Postgres, QueryBuilder};
 
-use crate::{BaseQuery, SqlValue, push_sqlvalue, push_jsonvalue};
+use crate::{BaseQuery, Expression, tag_comment, SqlValue, WhitespaceStyle, WindowSpec, is_valid_identifier, window_clause, bind_count, max_placeholder, push_expression, quote_ident};
+
+/// fields allowed in `EXTRACT(field FROM column)`
+pub const EXTRACT_FIELDS: [&str; 22] = [
+    "CENTURY",
+    "DAY",
+    "DECADE",
+    "DOW",
+    "DOY",
+    "EPOCH",
+    "HOUR",
+    "ISODOW",
+    "ISOYEAR",
+    "JULIAN",
+    "MICROSECONDS",
+    "MILLENNIUM",
+    "MILLISECONDS",
+    "MINUTE",
+    "MONTH",
+    "QUARTER",
+    "SECOND",
+    "TIMEZONE",
+    "TIMEZONE_HOUR",
+    "TIMEZONE_MINUTE",
+    "WEEK",
+    "YEAR",
+];
 
 #[derive(Debug, Clone)]
 pub struct Condition<'a> {
@@ -10,6 +40,35 @@ pub struct Condition<'a> {
     pub eq_opr: &'a str,
     pub value_l: SqlValue,
     pub value_r: Option<SqlValue>,
+    /// if provided, condition rendered from expression instead of column, eq_opr and values
+    pub expression: Option<Expression>,
+    /// wildcards added to string value of LIKE operators
+    pub like_mode: LikeMode,
+}
+
+/// how string value of LIKE, ILIKE, NOT LIKE .. conditions is turned into a pattern
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum LikeMode {
+    /// `%value%`
+    #[default]
+    Contains,
+    /// `value%`
+    StartsWith,
+    /// `%value`
+    EndsWith,
+    /// value is already a pattern and bound as is
+    Raw,
+}
+
+impl LikeMode {
+    pub fn pattern(&self, value: &str) -> String {
+        match self {
+            LikeMode::Contains => format!("%{value}%"),
+            LikeMode::StartsWith => format!("{value}%"),
+            LikeMode::EndsWith => format!("%{value}"),
+            LikeMode::Raw => value.to_string(),
+        }
+    }
 }
 
 impl<'a> Condition<'a> {
@@ -31,321 +90,1324 @@ impl<'a> Condition<'a> {
             eq_opr,
             value_l,
             value_r,
+            expression: None,
+            like_mode: LikeMode::default(),
         }
     }
-}
 
-/// if only one condition provided, then chain operator ignored for that condition
-pub struct ConditionBuilder<'a> {
-    pub base_query: BaseQuery<'a>,
-    pub conditions: &'a Vec<Condition<'a>>,
-    pub middle: Option<&'a str>,
-    pub limit: Option<i64>,
-    pub offset: Option<i64>,
-    pub end: Option<&'a str>,
-}
+    /// `column = $1` without chain operator, see `and_eq` and `or_eq`
+    pub fn eq(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(None, column, "=", value.into(), None)
+    }
 
-impl<'a> ConditionBuilder<'a> {
-    pub fn new(
-        base_query: BaseQuery<'a>,
-        conditions: &'a Vec<Condition<'a>>,
-        middle: Option<&'a str>,
-        limit: Option<i64>,
-        offset: Option<i64>,
-        end: Option<&'a str>,
+    pub fn and_eq(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("AND"), column, "=", value.into(), None)
+    }
+
+    pub fn or_eq(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("OR"), column, "=", value.into(), None)
+    }
+
+    /// `column > $1` without chain operator, see `and_gt` and `or_gt`
+    pub fn gt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(None, column, ">", value.into(), None)
+    }
+
+    pub fn and_gt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("AND"), column, ">", value.into(), None)
+    }
+
+    pub fn or_gt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("OR"), column, ">", value.into(), None)
+    }
+
+    /// `column < $1` without chain operator, see `and_lt` and `or_lt`
+    pub fn lt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(None, column, "<", value.into(), None)
+    }
+
+    pub fn and_lt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("AND"), column, "<", value.into(), None)
+    }
+
+    pub fn or_lt(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("OR"), column, "<", value.into(), None)
+    }
+
+    /// `column >= $1` without chain operator, see `and_gte` and `or_gte`
+    pub fn gte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(None, column, ">=", value.into(), None)
+    }
+
+    pub fn and_gte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("AND"), column, ">=", value.into(), None)
+    }
+
+    pub fn or_gte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("OR"), column, ">=", value.into(), None)
+    }
+
+    /// `column <= $1` without chain operator, see `and_lte` and `or_lte`
+    pub fn lte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(None, column, "<=", value.into(), None)
+    }
+
+    pub fn and_lte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("AND"), column, "<=", value.into(), None)
+    }
+
+    pub fn or_lte(column: &'a str, value: impl Into<SqlValue>) -> Self {
+        Self::new(Some("OR"), column, "<=", value.into(), None)
+    }
+
+    /// expression is used as the left side of condition ex.: `WHERE (CASE WHEN premium THEN $1 ELSE $2 END) > $3`
+    pub fn expression(
+        chain_opr: Option<&'a str>,
+        left: impl Into<Expression>,
+        eq_opr: &'a str,
+        value: SqlValue,
     ) -> Self {
-        Self {
-            base_query,
-            conditions,
-            middle,
-            limit,
-            offset,
-            end,
-        }
+        let expression = left.into().sql(format!(" {0} ", eq_opr)).value(value);
+
+        Self::from_expression(chain_opr, "", eq_opr, expression)
     }
 
-    pub fn build(self) -> QueryBuilder<'a, Postgres> {
-        let mut query: QueryBuilder<'_, Postgres>;
+    /// applies function to column before comparison ex.: `round(amount, 2) = $1`
+    /// extra_args: raw sql arguments after column
+    pub fn func_cmp(
+        chain_opr: Option<&'a str>,
+        func: &'a str,
+        column: &'a str,
+        extra_args: Vec<&'a str>,
+        eq_opr: &'a str,
+        value: SqlValue,
+    ) -> Self {
+        let mut args = vec![column];
+        args.extend(extra_args);
 
-        match self.base_query {
-            BaseQuery::Sql(base_sql) => query = QueryBuilder::new(base_sql),
-            BaseQuery::QueryBuilder(query_builder) => query = query_builder,
-        }
-
-        for (index, cond) in self.conditions.iter().enumerate() {
-            match cond.eq_opr.to_uppercase().as_str() {
-                "BETWEEN" => {
-                    if let Some(value_r) = &cond.value_r {
-                        if index == 0 {
-                            query.push("\nWHERE");
-                            query.push(format!("\n    {0} {1} ", cond.column, cond.eq_opr));
-                            
-                            query = push_sqlvalue(cond.value_l.clone(), query);
-                            query.push(" AND ");
-                            query = push_sqlvalue(value_r.clone(), query);
-                        } else if let Some(chain_opr) = cond.chain_opr {
-                            query.push(format!(
-                                "\n    {0} {1} {2} ",
-                                chain_opr, cond.column, cond.eq_opr
-                            ));
-
-                            query = push_sqlvalue(cond.value_l.clone(), query);
-                            query.push(" AND ");
-                            query = push_sqlvalue(value_r.clone(), query);
-                        }
-                    }
-                },
+        let expression = Expression::new()
+            .sql(format!("{0}({1}) {2} ", func, args.join(", "), eq_opr))
+            .value(value);
 
-                "IN" => {
-                    if index == 0 {
-                        if let SqlValue::GenericValue(Value::Array(item_list)) = cond.value_l.clone() {
-                            query.push("\nWHERE");
-                            query.push(format!("\n    {0} {1} ", cond.column, cond.eq_opr));
-
-                            query = Self::push_as_sql_tuple(item_list, query);
-                        }
-                    } else if let Some(chain_opr) = cond.chain_opr {
-                        if let SqlValue::GenericValue(Value::Array(item_list)) = cond.value_l.clone() {
-                            query.push(format!(
-                                "\n    {0} {1} {2} ",
-                                chain_opr, cond.column, cond.eq_opr
-                            ));
-
-                            query = Self::push_as_sql_tuple(item_list, query);
-                        }
-                    }
-                },
+        Self::from_expression(chain_opr, column, eq_opr, expression)
+    }
 
-                operator if operator.contains("LIKE") => {
-                    if index == 0 {
-                        query.push("\nWHERE");
-                        query.push(format!("\n    {0} {1} ", cond.column, cond.eq_opr));
-                        
-                        let like_value: String;
-
-                        if let SqlValue::GenericValue(Value::String(value)) = cond.value_l.clone() {
-                            like_value = format!("%{value}%");
-                        } else {
-                            like_value = String::new();
-                        }
-
-                        query = push_sqlvalue(like_value.into(), query);
-                    } else if let Some(chain_opr) = cond.chain_opr {
-                        query.push(format!(
-                            "\n    {0} {1} {2} ",
-                            chain_opr, cond.column, cond.eq_opr
-                        ));
-                        query = push_sqlvalue(cond.value_l.clone(), query);
-                    }
-                },
+    /// array column has at least one element, NULL arrays do not match
+    pub fn array_not_empty(chain_opr: Option<&'a str>, column: &'a str) -> Self {
+        let expression = Expression::new().sql(format!("cardinality({0}) > 0", column));
 
-                _ => {
-                    if index == 0 {
-                        query.push("\nWHERE");
-                        query.push(format!("\n    {0} {1} ", cond.column, cond.eq_opr));
-                        query = push_sqlvalue(cond.value_l.clone(), query);
-                    } else if let Some(chain_opr) = cond.chain_opr {
-                        query.push(format!(
-                            "\n    {0} {1} {2} ",
-                            chain_opr, cond.column, cond.eq_opr
-                        ));
-                        query = push_sqlvalue(cond.value_l.clone(), query);
-                    } 
-                }
+        Self::from_expression(chain_opr, column, ">", expression)
+    }
+
+    /// array column has no elements, NULL arrays do not match
+    pub fn array_empty(chain_opr: Option<&'a str>, column: &'a str) -> Self {
+        let expression = Expression::new().sql(format!("cardinality({0}) = 0", column));
+
+        Self::from_expression(chain_opr, column, "=", expression)
+    }
+
+    /// period of two columns overlaps with bound period ex.: `(starts_at, ends_at) OVERLAPS ($1, $2)`
+    pub fn overlaps(
+        chain_opr: Option<&'a str>,
+        columns: (&'a str, &'a str),
+        values: (SqlValue, SqlValue),
+    ) -> Self {
+        let expression = Expression::new()
+            .sql(format!("({0}, {1}) OVERLAPS (", columns.0, columns.1))
+            .value(values.0)
+            .sql(", ")
+            .value(values.1)
+            .sql(")");
+
+        Self::from_expression(chain_opr, columns.0, "OVERLAPS", expression)
+    }
+
+    /// case insensitive equality usable with functional index on lower(column) ex.: `lower(email) = lower($1)`
+    pub fn ci_eq(chain_opr: Option<&'a str>, column: &'a str, value: SqlValue) -> Self {
+        let expression = Expression::new()
+            .sql(format!("lower({0}) = lower(", column))
+            .value(value)
+            .sql(")");
+
+        Self::from_expression(chain_opr, column, "=", expression)
+    }
+
+    /// compares the biggest of columns ex.: `GREATEST(updated_at, created_at) > $1`, error if columns are empty
+    pub fn greatest(chain_opr: Option<&'a str>, columns: Vec<&'a str>, eq_opr: &'a str, value: SqlValue) -> Result<Self, ConditionError> {
+        Self::variadic_cmp(chain_opr, "GREATEST", columns, eq_opr, value)
+    }
+
+    /// compares the smallest of columns ex.: `LEAST(price, discount_price) <= $1`, error if columns are empty
+    pub fn least(chain_opr: Option<&'a str>, columns: Vec<&'a str>, eq_opr: &'a str, value: SqlValue) -> Result<Self, ConditionError> {
+        Self::variadic_cmp(chain_opr, "LEAST", columns, eq_opr, value)
+    }
+
+    fn variadic_cmp(
+        chain_opr: Option<&'a str>,
+        func: &'a str,
+        columns: Vec<&'a str>,
+        eq_opr: &'a str,
+        value: SqlValue,
+    ) -> Result<Self, ConditionError> {
+        let column = match columns.first() {
+            Some(column) => *column,
+            None => return Err(ConditionError::EmptyColumns),
+        };
+        let expression = Expression::new()
+            .sql(format!("{0}({1}) {2} ", func, columns.join(", "), eq_opr))
+            .value(value);
+
+        Ok(Self::from_expression(chain_opr, column, eq_opr, expression))
+    }
+
+    /// IN which also matches NULL when the list contains null ex.: `(col IN ($1, $2) OR col IS NULL)`
+    /// scalar value is a one item list, rendered as `col = $1` or `col IS NULL` for null
+    pub fn in_null_safe(chain_opr: Option<&'a str>, column: &'a str, values: SqlValue) -> Self {
+        let items = match values {
+            SqlValue::GenericValue(Value::Array(items)) => items,
+            SqlValue::GenericValue(Value::Null) => vec![Value::Null],
+            #[cfg(feature = "uuid")]
+            value @ SqlValue::UuidArray(_) => return Self::new(chain_opr, column, "IN", value, None),
+            value => return Self::new(chain_opr, column, "=", value, None),
+        };
+
+        let has_null = items.iter().any(Value::is_null);
+        let items: Vec<Value> = items.into_iter().filter(|item| !item.is_null()).collect();
+
+        let mut in_list = Expression::new().sql(format!("{0} IN (", column));
+
+        for (index, item) in items.iter().enumerate() {
+            if index > 0 {
+                in_list = in_list.sql(", ");
             }
+
+            in_list = in_list.value(item);
         }
 
-        if let Some(middle_sql) = self.middle {
-            query.push(format!("\n{}", middle_sql));
+        in_list = in_list.sql(")");
+
+        let expression = match (items.is_empty(), has_null) {
+            (true, true) => Expression::new().sql(format!("{0} IS NULL", column)),
+            (true, false) => Expression::new().sql("FALSE"),
+            (false, true) => Expression::new()
+                .sql("(")
+                .expression(in_list)
+                .sql(format!(" OR {0} IS NULL)", column)),
+            (false, false) => in_list,
+        };
+
+        Self::from_expression(chain_opr, column, "IN", expression)
+    }
+
+    /// column contains literal substring, no LIKE escaping needed ex.: `strpos(title, $1) > 0`
+    pub fn contains_substring(chain_opr: Option<&'a str>, column: &'a str, value: SqlValue) -> Self {
+        let expression = Expression::new()
+            .sql(format!("strpos({0}, ", column))
+            .value(value)
+            .sql(") > 0");
+
+        Self::from_expression(chain_opr, column, "strpos", expression)
+    }
+
+    /// column matches any of patterns, no patterns matches nothing ex.: `(title ILIKE $1 OR title ILIKE $2)`
+    pub fn like_patterns(
+        chain_opr: Option<&'a str>,
+        column: &'a str,
+        patterns: Vec<String>,
+        case_insensitive: bool,
+    ) -> Self {
+        let eq_opr = if case_insensitive { "ILIKE" } else { "LIKE" };
+
+        if patterns.is_empty() {
+            return Self::from_expression(chain_opr, column, eq_opr, Expression::new().sql("FALSE"));
         }
 
-        if let Some(limit) = self.limit {
-            query.push("\nLIMIT ");
-            query.push_bind(limit);
+        let mut expression = Expression::new().sql("(");
+
+        for (index, pattern) in patterns.into_iter().enumerate() {
+            if index > 0 {
+                expression = expression.sql(" OR ");
+            }
+
+            expression = expression.sql(format!("{0} {1} ", column, eq_opr)).value(pattern);
         }
 
-        if let Some(offset) = self.offset {
-            query.push("\nOFFSET ");
-            query.push_bind(offset);
+        Self::from_expression(chain_opr, column, eq_opr, expression.sql(")"))
+    }
+
+    /// geography column is within distance (meters) of point given as WKT text
+    /// ex.: `ST_DWithin(location, ST_GeogFromText($1), $2)` with `'SRID=4326;POINT(13.4 52.5)'`
+    #[cfg(feature = "postgis")]
+    pub fn st_dwithin(chain_opr: Option<&'a str>, column: &'a str, point: SqlValue, distance: SqlValue) -> Self {
+        let expression = Expression::new()
+            .sql(format!("ST_DWithin({0}, ST_GeogFromText(", column))
+            .value(point)
+            .sql("), ")
+            .value(distance)
+            .sql(")");
+
+        Self::from_expression(chain_opr, column, "ST_DWithin", expression)
+    }
+
+    /// column is within pct percent of center, center is bound once, SYMMETRIC keeps negative centers working
+    /// ex.: pct 10.0 gives `price BETWEEN SYMMETRIC $1 * 0.9 AND $1 * 1.1`, error if pct is not finite
+    pub fn within_pct(chain_opr: Option<&'a str>, column: &'a str, center: SqlValue, pct: f64) -> Result<Self, ConditionError> {
+        if !pct.is_finite() {
+            return Err(ConditionError::InvalidPercent(pct));
         }
 
-        if let Some(ending) = self.end {
-            query.push(format!("\n{}", ending));
+        let pct = pct.abs();
+        let expression = Expression::new()
+            .sql(format!("{0} BETWEEN SYMMETRIC ", column))
+            .value(center)
+            .sql(format!(" * {0} AND ", (100.0 - pct) / 100.0))
+            .bound_value(0)
+            .sql(format!(" * {0}", (100.0 + pct) / 100.0));
+
+        Ok(Self::from_expression(chain_opr, column, "BETWEEN", expression))
+    }
+
+    /// `column = $1` for Some value, `column IS NULL` for None or null ex.: optional foreign keys
+    pub fn eq_or_null(chain_opr: Option<&'a str>, column: &'a str, value: Option<SqlValue>) -> Self {
+        match value {
+            Some(SqlValue::GenericValue(Value::Null)) | None => Self::is_null(chain_opr, column),
+            Some(value) => Self::new(chain_opr, column, "=", value, None),
         }
+    }
+
+    /// column equals any of values of a postgres enum ex.: `status = ANY($1::status_enum[])`
+    /// values are bound as one array literal text which postgres casts to the enum array
+    pub fn enum_any(chain_opr: Option<&'a str>, column: &'a str, type_name: &'a str, values: Vec<String>) -> Self {
+        let expression = Expression::new()
+            .sql(format!("{0} = ANY(", column))
+            .value(array_literal(&values))
+            .sql(format!("::{0}[])", type_name));
 
-        query
+        Self::from_expression(chain_opr, column, "ANY", expression)
     }
 
-    fn push_as_sql_tuple(item_list: Vec<Value>, mut query: QueryBuilder<'a, Postgres>) -> QueryBuilder<'a, Postgres> {
-        query.push("(");
-        
-        for (index, item) in item_list.iter().enumerate() {
-            query = push_jsonvalue(item.clone(), query);
+    /// pg_trgm fuzzy match, `name % $1` with database similarity threshold or `similarity(name, $1) > $2`
+    pub fn trigram_similar(chain_opr: Option<&'a str>, column: &'a str, value: SqlValue, threshold: Option<f64>) -> Self {
+        let expression = match threshold {
+            Some(threshold) => Expression::new()
+                .sql(format!("similarity({0}, ", column))
+                .value(value)
+                .sql(") > ")
+                .value(Value::from(threshold)),
+            None => Expression::new().sql(format!("{0} % ", column)).value(value),
+        };
 
-            if index < item_list.len() - 1 {
-                query.push(", ");
-            }
+        Self::from_expression(chain_opr, column, "%", expression)
+    }
+
+    /// compares a date part of column ex.: `EXTRACT(YEAR FROM created_at) = $1`
+    pub fn extract(
+        chain_opr: Option<&'a str>,
+        field: &str,
+        column: &'a str,
+        eq_opr: &'a str,
+        value: SqlValue,
+    ) -> Result<Self, ConditionError> {
+        let field = field.to_uppercase();
+
+        if !EXTRACT_FIELDS.contains(&field.as_str()) {
+            return Err(ConditionError::InvalidExtractField(field));
         }
 
-        query.push(")");
+        let expression = Expression::new()
+            .sql(format!("EXTRACT({0} FROM {1}) {2} ", field, column, eq_opr))
+            .value(value);
 
-        query
+        Ok(Self::from_expression(chain_opr, column, eq_opr, expression))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::condition::{Condition, ConditionBuilder};
-    use crate::general::BaseQuery;
+    /// jsonb array column contains scalar value ex.: `tags @> $1::jsonb` with `["rust"]`
+    pub fn json_array_contains(chain_opr: Option<&'a str>, column: &'a str, value: Value) -> Self {
+        let expression = Expression::new()
+            .sql(format!("{0} @> ", column))
+            .value(Value::Array(vec![value]).to_string())
+            .sql("::jsonb");
 
-    #[test]
-    fn between_with_where() {
-        let mut conditions: Vec<Condition> = Vec::new();
+        Self::from_expression(chain_opr, column, "@>", expression)
+    }
 
-        conditions.push(Condition::new(
-            None,
-            "test_col",
-            "BETWEEN",
-            5.into(),
-            Some(24.into()),
-        ));
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+    /// bitmask column has all bits of mask set, mask bound once ex.: `(flags & $1) = $1`
+    pub fn has_all_flags(chain_opr: Option<&'a str>, column: &'a str, mask: SqlValue) -> Self {
+        let expression = Expression::new()
+            .sql(format!("({0} & ", column))
+            .value(mask)
+            .sql(") = ")
+            .bound_value(0);
 
-        let result = "\nWHERE\n    test_col BETWEEN $1 AND $2";
+        Self::from_expression(chain_opr, column, "&", expression)
+    }
 
-        assert_eq!(test_query.build().into_sql(), result);
+    /// bitmask column has any bit of mask set ex.: `(flags & $1) <> 0`
+    pub fn has_any_flags(chain_opr: Option<&'a str>, column: &'a str, mask: SqlValue) -> Self {
+        let expression = Expression::new()
+            .sql(format!("({0} & ", column))
+            .value(mask)
+            .sql(") <> 0");
+
+        Self::from_expression(chain_opr, column, "&", expression)
     }
 
-    #[test]
-    fn single_condition_with_where() {
-        let mut conditions: Vec<Condition> = Vec::new();
+    /// array column shares at least n distinct elements with values, compared as text
+    /// ex.: `cardinality(array(SELECT unnest(tags)::text INTERSECT SELECT unnest($1::text[]))) >= $2`
+    pub fn array_intersection_at_least(chain_opr: Option<&'a str>, column: &'a str, values: Vec<Value>, n: i64) -> Self {
+        let items: Vec<String> = values
+            .into_iter()
+            .filter(|value| !value.is_null())
+            .map(|value| match value {
+                Value::String(v) => v,
+                v => v.to_string(),
+            })
+            .collect();
 
-        conditions.push(Condition::new(
-            None,
-            "test_col",
-            "LIKE",
-            "sample".into(),
-            None,
-        ));
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+        let expression = Expression::new()
+            .sql(format!("cardinality(array(SELECT unnest({0})::text INTERSECT SELECT unnest(", column))
+            .value(array_literal(&items))
+            .sql("::text[]))) >= ")
+            .value(n);
 
-        let result = "\nWHERE\n    test_col LIKE $1";
+        Self::from_expression(chain_opr, column, ">=", expression)
+    }
 
-        assert_eq!(test_query.build().into_sql(), result);
+    /// LIKE condition with wildcards added by mode ex.: `LikeMode::StartsWith` binds `value%`
+    pub fn like(chain_opr: Option<&'a str>, column: &'a str, value: impl Into<String>, like_mode: LikeMode) -> Self {
+        let mut condition = Self::new(chain_opr, column, "LIKE", value.into().into(), None);
+        condition.like_mode = like_mode;
+
+        condition
     }
 
-    #[test]
-    fn single_condition_with_in_operator() {
-        let mut conditions: Vec<Condition> = Vec::new();
-        
-        let list: Vec<&str> = vec!["ab", "cd", "ef"];
+    /// text column starts with prefix as index friendly range ex.: `(name >= $1 AND name < $2)` with `abc`, `abd`
+    /// upper bound increments last char which can be incremented, only lower bound if there is none
+    pub fn prefix_range(chain_opr: Option<&'a str>, column: &'a str, prefix: &str) -> Self {
+        let mut upper: Vec<char> = prefix.chars().collect();
 
-        conditions.push(Condition::new(
-            None,
-            "test_col",
-            "IN",
-            list.into(),
-            None,
-        ));
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+        let upper = loop {
+            match upper.pop() {
+                Some(last) => {
+                    let next = (last as u32 + 1..=char::MAX as u32).find_map(char::from_u32);
 
-        let result = "\nWHERE\n    test_col IN ($1, $2, $3)";
+                    if let Some(next) = next {
+                        upper.push(next);
+                        break Some(upper.into_iter().collect::<String>());
+                    }
+                },
+                None => break None,
+            }
+        };
 
-        assert_eq!(test_query.build().into_sql(), result);
+        let expression = match upper {
+            Some(upper) => Expression::new()
+                .sql(format!("({0} >= ", column))
+                .value(prefix)
+                .sql(format!(" AND {0} < ", column))
+                .value(upper)
+                .sql(")"),
+            None => Expression::new().sql(format!("{0} >= ", column)).value(prefix),
+        };
+
+        Self::from_expression(chain_opr, column, ">=", expression)
     }
 
-    #[test]
-    fn single_condition_without_where() {
-        let mut conditions: Vec<Condition> = Vec::new();
+    /// `column IS NULL`, nothing is bound
+    pub fn is_null(chain_opr: Option<&'a str>, column: &'a str) -> Self {
+        Self::new(chain_opr, column, "IS NULL", Value::Null.into(), None)
+    }
 
-        conditions.push(Condition::new(
-            Some(""),
-            "test_col",
-            "LIKE",
-            "sample".into(),
-            None,
-        ));
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+    /// `column IS NOT NULL`, nothing is bound
+    pub fn is_not_null(chain_opr: Option<&'a str>, column: &'a str) -> Self {
+        Self::new(chain_opr, column, "IS NOT NULL", Value::Null.into(), None)
+    }
 
-        let result = "\nWHERE\n    test_col LIKE $1";
+    /// current time is within period of two columns, nothing is bound ex.: `now() BETWEEN starts_at AND ends_at`
+    pub fn now_between(chain_opr: Option<&'a str>, start_column: &'a str, end_column: &'a str) -> Self {
+        let expression = Expression::new().sql(format!("now() BETWEEN {0} AND {1}", start_column, end_column));
 
-        assert_eq!(test_query.build().into_sql(), result);
+        Self::from_expression(chain_opr, start_column, "BETWEEN", expression)
     }
 
-    #[test]
-    fn single_condition_with_chain_operand() {
-        let mut conditions: Vec<Condition> = Vec::new();
+    /// type of jsonb value at path ex.: `jsonb_typeof(data->'address'->'zip') = $1` with `string`
+    pub fn json_typeof(chain_opr: Option<&'a str>, column: &'a str, path: Vec<&str>, expected_type: &str) -> Self {
+        let keys: String = path
+            .iter()
+            .map(|key| format!("->'{0}'", key.replace('\'', "''")))
+            .collect();
 
-        conditions.push(Condition::new(
-            Some("AND"),
-            "test_col",
-            "LIKE",
-            "sample".into(),
-            None,
-        ));
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+        let expression = Expression::new()
+            .sql(format!("jsonb_typeof({0}{1}) = ", column, keys))
+            .value(expected_type);
 
-        let result = "\nWHERE\n    test_col LIKE $1";
+        Self::from_expression(chain_opr, column, "=", expression)
+    }
 
-        assert_eq!(test_query.build().into_sql(), result);
+    /// column compared with result of scalar subquery, subquery values are bound in place
+    /// ex.: `score > (SELECT avg(score) FROM results WHERE season = $1)`
+    pub fn cmp_subquery(chain_opr: Option<&'a str>, column: &'a str, eq_opr: &'a str, subquery: Expression) -> Self {
+        let expression = Expression::new()
+            .sql(format!("{0} {1} (", column, eq_opr))
+            .expression(subquery)
+            .sql(")");
+
+        Self::from_expression(chain_opr, column, eq_opr, expression)
     }
 
-    #[test]
-    fn empty_condition_builder() {
-        let conditions: Vec<Condition> = Vec::new();
-        let test_query =
-            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+    /// column is a descendant of root in tree table, root itself is not included
+    /// ex.: `id IN (WITH RECURSIVE descendants AS (SELECT id FROM categories WHERE parent_id = $1 UNION ALL SELECT t.id FROM categories t JOIN descendants d ON t.parent_id = d.id) SELECT id FROM descendants)`
+    pub fn descendant_of(
+        chain_opr: Option<&'a str>,
+        column: &'a str,
+        table: &str,
+        id_column: &str,
+        parent_column: &str,
+        root: SqlValue,
+    ) -> Self {
+        let expression = Expression::new()
+            .sql(format!(
+                "{0} IN (WITH RECURSIVE descendants AS (SELECT {2} FROM {1} WHERE {3} = ",
+                column, table, id_column, parent_column
+            ))
+            .value(root)
+            .sql(format!(
+                " UNION ALL SELECT t.{1} FROM {0} t JOIN descendants d ON t.{2} = d.{1}) SELECT {1} FROM descendants)",
+                table, id_column, parent_column
+            ));
 
-        assert_eq!(test_query.build().into_sql(), "");
+        Self::from_expression(chain_opr, column, "IN", expression)
     }
 
-    #[test]
-    fn multiple_conditions() {
-        let mut conditions: Vec<Condition> = Vec::new();
+    /// column is within tolerance of center, center is bound once, tolerance is rendered as interval literal
+    /// ex.: `happened_at BETWEEN $1 - interval '300 seconds' AND $1 + interval '300 seconds'`
+    pub fn near_time(chain_opr: Option<&'a str>, column: &'a str, center: NaiveDateTime, tolerance: Duration) -> Self {
+        let milliseconds = tolerance.num_milliseconds().abs();
+        let interval = if milliseconds % 1000 == 0 {
+            format!("interval '{0} seconds'", milliseconds / 1000)
+        } else {
+            format!("interval '{0} milliseconds'", milliseconds)
+        };
 
-        conditions.push(Condition::new(
-            Some("AND"),
-            "test_col",
-            "LIKE",
-            "sample".into(),
-            None,
-        ));
-        conditions.push(Condition::new(
-            Some("OR"),
-            "test_col2",
-            "=",
-            5.into(),
-            None,
-        ));
+        let expression = Expression::new()
+            .sql(format!("{0} BETWEEN ", column))
+            .value(center)
+            .sql(format!(" - {0} AND ", interval))
+            .bound_value(0)
+            .sql(format!(" + {0}", interval));
 
-        // This condition will be ignored because there is no chain operator
-        conditions.push(Condition::new(
-            None,
-            "other_col",
-            "=",
-            7.into(),
-            None
-        ));
+        Self::from_expression(chain_opr, column, "BETWEEN", expression)
+    }
 
-        let order_by = "ORDER BY\n    id DESC";
+    /// numeric column has no fractional part, nothing is bound ex.: `amount = floor(amount)`
+    pub fn is_whole(chain_opr: Option<&'a str>, column: &'a str) -> Self {
+        let expression = Expression::new().sql(format!("{0} = floor({0})", column));
 
-        let test_query = ConditionBuilder::new(
-            BaseQuery::Sql(""),
-            &conditions,
-            Some(order_by),
-            Some(10),
-            Some(0),
-            None,
-        );
+        Self::from_expression(chain_opr, column, "=", expression)
+    }
 
-        let result = r#"
-WHERE
-    test_col LIKE $1
+    /// number of characters of column is within bounds, both bounds are bound
+    /// ex.: `char_length(name) BETWEEN $1 AND $2`
+    pub fn length_between(chain_opr: Option<&'a str>, column: &'a str, min: i64, max: i64) -> Self {
+        let expression = Expression::new()
+            .sql(format!("char_length({0}) BETWEEN ", column))
+            .value(min)
+            .sql(" AND ")
+            .value(max);
+
+        Self::from_expression(chain_opr, column, "BETWEEN", expression)
+    }
+
+    /// jsonb column equals document, key order and whitespace are ignored ex.: `data = $1::jsonb`
+    pub fn json_eq(chain_opr: Option<&'a str>, column: &'a str, value: Value) -> Self {
+        let expression = Expression::new()
+            .sql(format!("{0} = ", column))
+            .value(value.to_string())
+            .sql("::jsonb");
+
+        Self::from_expression(chain_opr, column, "=", expression)
+    }
+
+    /// values bound when the condition is rendered, in order ex.: both bounds of BETWEEN, each item of IN list
+    pub fn bound_values(&self) -> Vec<SqlValue> {
+        self.to_expression()
+            .map(|expression| expression.bound_values())
+            .unwrap_or_default()
+    }
+
+    /// condition as rendered by `ConditionBuilder` without chain operator ex.: `col BETWEEN $1 AND $2`,
+    /// None if it is not rendered, see `renders`
+    pub fn to_expression(&self) -> Option<Expression> {
+        if let Some(expression) = &self.expression {
+            return Some(expression.clone());
+        }
+
+        let operation = Expression::new().sql(format!("{0} {1} ", self.column, self.eq_opr));
+
+        match self.eq_opr.to_uppercase().as_str() {
+            "BETWEEN" | "NOT BETWEEN" => self
+                .value_r
+                .as_ref()
+                .map(|value_r| operation.value(self.value_l.clone()).sql(" AND ").value(value_r.clone())),
+            "IS NULL" | "IS NOT NULL" => Some(Expression::new().sql(format!("{0} {1}", self.column, self.eq_opr))),
+            operator if operator.ends_with("IN") => in_list(&self.value_l).map(|items| {
+                // `IN ()` is a syntax error, empty list matches nothing for IN and everything for NOT IN
+                if items.is_empty() {
+                    return Expression::new().sql(if operator.starts_with("NOT") { "TRUE" } else { "FALSE" });
+                }
+
+                let mut tuple = operation.sql("(");
+
+                for (index, item) in items.into_iter().enumerate() {
+                    if index > 0 {
+                        tuple = tuple.sql(", ");
+                    }
+
+                    tuple = tuple.value(item);
+                }
+
+                tuple.sql(")")
+            }),
+            operator if operator.contains("LIKE") => match &self.value_l {
+                SqlValue::GenericValue(Value::String(value)) => Some(operation.value(self.like_mode.pattern(value))),
+                value => Some(operation.value(value.clone())),
+            },
+            _ => Some(operation.value(self.value_l.clone())),
+        }
+    }
+
+    /// row with composite key exists in table, values are bound
+    /// ex.: `EXISTS (SELECT 1 FROM memberships WHERE org_id = $1 AND user_id = $2)`, see `select_exists_by_key`
+    /// error if key is empty
+    pub fn exists_by_key(chain_opr: Option<&'a str>, table: &'a str, key: Vec<(&str, SqlValue)>) -> Result<Self, ConditionError> {
+        if key.is_empty() {
+            return Err(ConditionError::EmptyColumns);
+        }
+
+        let mut expression = Expression::new().sql(format!("EXISTS (SELECT 1 FROM {0} WHERE ", table));
+
+        for (index, (column, value)) in key.into_iter().enumerate() {
+            if index > 0 {
+                expression = expression.sql(" AND ");
+            }
+
+            expression = expression.sql(format!("{0} = ", column)).value(value);
+        }
+
+        Ok(Self::from_expression(chain_opr, table, "EXISTS", expression.sql(")")))
+    }
+
+    /// compares column with session setting, nothing is bound ex.: `tenant_id = current_setting('app.tenant_id')::int`
+    /// setting name and cast must be plain identifiers as they are rendered as literals
+    pub fn against_setting(
+        chain_opr: Option<&'a str>,
+        column: &'a str,
+        eq_opr: &'a str,
+        setting_name: &str,
+        cast: Option<&str>,
+    ) -> Result<Self, ConditionError> {
+        if !is_valid_identifier(setting_name) {
+            return Err(ConditionError::InvalidSetting(setting_name.to_string()));
+        }
+
+        let cast = match cast {
+            Some(cast) if !is_valid_identifier(cast) => return Err(ConditionError::InvalidSetting(cast.to_string())),
+            Some(cast) => format!("::{0}", cast),
+            None => String::new(),
+        };
+
+        let expression = Expression::new()
+            .sql(format!("{0} {1} current_setting('{2}'){3}", column, eq_opr, setting_name, cast));
+
+        Ok(Self::from_expression(chain_opr, column, eq_opr, expression))
+    }
+
+    /// invalid conditions are skipped by `ConditionBuilder` ex.: BETWEEN without value_r, IN with a scalar
+    pub fn renders(&self) -> bool {
+        self.to_expression().is_some()
+    }
+
+    /// column and eq_opr are only informative, condition is rendered from expression
+    pub fn from_expression(
+        chain_opr: Option<&'a str>,
+        column: &'a str,
+        eq_opr: &'a str,
+        expression: Expression,
+    ) -> Self {
+        Self {
+            chain_opr,
+            column,
+            eq_opr,
+            value_l: SqlValue::GenericValue(Value::Null),
+            value_r: None,
+            expression: Some(expression),
+            like_mode: LikeMode::default(),
+        }
+    }
+}
+
+/// standalone existence check of composite key ex.: `SELECT EXISTS (SELECT 1 FROM t WHERE a = $1 AND b = $2)`
+/// error if key is empty
+pub fn select_exists_by_key<'a>(table: &'a str, key: Vec<(&str, SqlValue)>) -> Result<QueryBuilder<'a, Postgres>, ConditionError> {
+    let condition = Condition::exists_by_key(None, table, key)?;
+    let query: QueryBuilder<'a, Postgres> = QueryBuilder::new("SELECT ");
+
+    match condition.to_expression() {
+        Some(expression) => Ok(push_expression(expression, query)),
+        None => Ok(query),
+    }
+}
+
+/// conditions grouped in parentheses ex.: `(a = $1 OR b = $2) AND c = $3`
+#[derive(Debug, Clone)]
+pub enum ConditionGroup<'a> {
+    Leaf(Condition<'a>),
+    /// chain operator of first condition of the group is ignored
+    Group {
+        chain_opr: Option<&'a str>,
+        conditions: Vec<ConditionGroup<'a>>,
+    },
+    /// negated group ex.: `NOT (a = $1 OR b = $2)`
+    Not {
+        chain_opr: Option<&'a str>,
+        conditions: Vec<ConditionGroup<'a>>,
+    },
+}
+
+impl<'a> ConditionGroup<'a> {
+    pub fn chain_opr(&self) -> Option<&'a str> {
+        match self {
+            ConditionGroup::Leaf(cond) => cond.chain_opr,
+            ConditionGroup::Group { chain_opr, .. } | ConditionGroup::Not { chain_opr, .. } => *chain_opr,
+        }
+    }
+
+    /// invalid conditions (ex.: BETWEEN without value_r) and empty groups are not rendered
+    fn renders(&self) -> bool {
+        match self {
+            ConditionGroup::Leaf(cond) => cond.renders(),
+            ConditionGroup::Group { conditions, .. } | ConditionGroup::Not { conditions, .. } => {
+                conditions.iter().any(ConditionGroup::renders)
+            },
+        }
+    }
+
+    /// items as rendered, items after first one without chain operator are skipped
+    fn rendered(items: &[Self]) -> Vec<&Self> {
+        let mut rendered: Vec<&Self> = Vec::new();
+
+        for item in items.iter().filter(|item| item.renders()) {
+            if rendered.is_empty() || item.chain_opr().is_some() {
+                rendered.push(item);
+            }
+        }
+
+        rendered
+    }
+
+    /// values bound when the item is rendered, in order
+    pub fn bound_values(&self) -> Vec<SqlValue> {
+        match self {
+            ConditionGroup::Leaf(cond) => cond.bound_values(),
+            ConditionGroup::Group { conditions, .. } | ConditionGroup::Not { conditions, .. } => Self::rendered(conditions)
+                .into_iter()
+                .flat_map(ConditionGroup::bound_values)
+                .collect(),
+        }
+    }
+
+    fn leaves(&self) -> Vec<&Condition<'a>> {
+        match self {
+            ConditionGroup::Leaf(cond) => vec![cond],
+            ConditionGroup::Group { conditions, .. } | ConditionGroup::Not { conditions, .. } => {
+                conditions.iter().flat_map(ConditionGroup::leaves).collect()
+            },
+        }
+    }
+}
+
+impl<'a> From<Condition<'a>> for ConditionGroup<'a> {
+    fn from(value: Condition<'a>) -> Self {
+        Self::Leaf(value)
+    }
+}
+
+/// items of an IN list value, None if value is not a list
+fn in_list(value: &SqlValue) -> Option<Vec<SqlValue>> {
+    match value {
+        SqlValue::GenericValue(Value::Array(items)) => Some(items.iter().map(SqlValue::from).collect()),
+        #[cfg(feature = "uuid")]
+        SqlValue::UuidArray(items) => Some(items.iter().map(|item| SqlValue::Uuid(*item)).collect()),
+        _ => None,
+    }
+}
+
+/// text array literal with quoted items ex.: `{"a","b \"c\""}`, castable to any array type
+fn array_literal(values: &[String]) -> String {
+    let items: Vec<String> = values
+        .iter()
+        .map(|value| format!("\"{0}\"", value.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+
+    format!("{{{0}}}", items.join(","))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConditionError {
+    /// BETWEEN or NOT BETWEEN condition without value_r
+    MissingRangeBound { column: String },
+    /// field is not in `EXTRACT_FIELDS`
+    InvalidExtractField(String),
+    /// setting name or cast is not a plain identifier
+    InvalidSetting(String),
+    /// condition needs at least one column ex.: key of `exists_by_key`
+    EmptyColumns,
+    /// percent is NaN or infinite
+    InvalidPercent(f64),
+    /// base query has bound values which can not be bound again ex.: in `into_expression`
+    BoundBaseQuery,
+}
+
+impl fmt::Display for ConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConditionError::MissingRangeBound { column } => {
+                write!(f, "range condition on column `{column}` has no right bound (value_r)")
+            },
+            ConditionError::InvalidExtractField(field) => write!(f, "`{field}` is not an EXTRACT field"),
+            ConditionError::InvalidSetting(setting) => write!(f, "`{setting}` is not a valid setting name or cast"),
+            ConditionError::EmptyColumns => write!(f, "condition has no columns"),
+            ConditionError::InvalidPercent(pct) => write!(f, "`{pct}` is not a finite percent"),
+            ConditionError::BoundBaseQuery => write!(f, "base query has bound values"),
+        }
+    }
+}
+
+impl std::error::Error for ConditionError {}
+
+/// row locking clause ex.: `FOR NO KEY UPDATE OF users`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Locking {
+    Update,
+    NoKeyUpdate,
+    Share,
+    KeyShare,
+}
+
+impl Locking {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Locking::Update => "FOR UPDATE",
+            Locking::NoKeyUpdate => "FOR NO KEY UPDATE",
+            Locking::Share => "FOR SHARE",
+            Locking::KeyShare => "FOR KEY SHARE",
+        }
+    }
+}
+
+/// direction of ORDER BY term, NULLS variants are the ones differing from postgres defaults
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SortDirection {
+    Asc,
+    Desc,
+    AscNullsFirst,
+    DescNullsLast,
+}
+
+impl SortDirection {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SortDirection::Asc => "ASC",
+            SortDirection::Desc => "DESC",
+            SortDirection::AscNullsFirst => "ASC NULLS FIRST",
+            SortDirection::DescNullsLast => "DESC NULLS LAST",
+        }
+    }
+}
+
+/// ORDER BY terms in order ex.: `vec![("created_at", SortDirection::Desc), ("id", SortDirection::Asc)]`
+pub type OrderBy<'a> = Vec<(&'a str, SortDirection)>;
+
+/// GROUP BY or ORDER BY term with collation, collation is quoted ex.: `name COLLATE "und-x-icu"`
+/// for case insensitive grouping with a nondeterministic collation, or use `lower(name)` as term
+pub fn collate(term: &str, collation: &str) -> String {
+    format!("{0} COLLATE {1}", term, quote_ident(collation))
+}
+
+/// ORDER BY term, see `order_terms`
+#[derive(Debug, Clone)]
+pub enum OrderTerm<'a> {
+    Column(&'a str, SortDirection),
+    /// rows in order of values, whole list is bound as one text array so the sql does not depend on its length,
+    /// rows not in list go last ex.: `array_position($1::text[], id::text)`, other values are bound as they are
+    /// ex.: `array_position($1, id)` for `uuid[]`
+    ByArrayPosition { array_value: SqlValue, column: &'a str },
+}
+
+impl<'a> OrderTerm<'a> {
+    fn expression(&self) -> Expression {
+        match self {
+            OrderTerm::Column(column, direction) => Expression::new().sql(format!("{0} {1}", column, direction.as_sql())),
+            OrderTerm::ByArrayPosition { array_value: SqlValue::GenericValue(Value::Array(items)), column } => {
+                let items: Vec<String> = items
+                    .iter()
+                    .filter(|item| !item.is_null())
+                    .map(|item| match item {
+                        Value::String(v) => v.clone(),
+                        v => v.to_string(),
+                    })
+                    .collect();
+
+                Expression::new()
+                    .sql("array_position(")
+                    .value(array_literal(&items))
+                    .sql(format!("::text[], {0}::text)", column))
+            },
+            OrderTerm::ByArrayPosition { array_value, column } => Expression::new()
+                .sql("array_position(")
+                .value(array_value.clone())
+                .sql(format!(", {0})", column)),
+        }
+    }
+}
+
+/// if only one condition provided, then chain operator ignored for that condition
+pub struct ConditionBuilder<'a> {
+    pub base_query: BaseQuery<'a>,
+    pub conditions: &'a Vec<Condition<'a>>,
+    pub middle: Option<&'a str>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    pub end: Option<&'a str>,
+    /// rendered as trailing comment, see `tag_comment`
+    pub tag: Option<BTreeMap<&'a str, &'a str>>,
+    /// locking mode and tables it applies for (all tables if empty), rendered after LIMIT/OFFSET
+    pub lock: Option<(Locking, Vec<&'a str>)>,
+    /// rendered after conditions, chained like them
+    pub groups: Vec<ConditionGroup<'a>>,
+    /// rendered after middle and before LIMIT/OFFSET, nothing if empty
+    pub order_by: Vec<OrderTerm<'a>>,
+    /// `GROUP BY a, b` after conditions, terms are rendered as is ex.: `lower(name)`
+    pub group_by: Vec<&'a str>,
+    /// rendered after GROUP BY like conditions, aggregate goes to column ex.: `HAVING sum(amount) > $1`,
+    /// postgres does not accept select aliases here
+    pub having: Vec<Condition<'a>>,
+    /// named windows after HAVING ex.: `WINDOW w AS (PARTITION BY customer_id)`, see `SelectColumn::Window`
+    pub windows: Vec<(&'a str, WindowSpec<'a>)>,
+    /// line layout of built query, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
+}
+
+impl<'a> ConditionBuilder<'a> {
+    pub fn new(
+        base_query: BaseQuery<'a>,
+        conditions: &'a Vec<Condition<'a>>,
+        middle: Option<&'a str>,
+        limit: Option<i64>,
+        offset: Option<i64>,
+        end: Option<&'a str>,
+    ) -> Self {
+        Self {
+            base_query,
+            conditions,
+            middle,
+            limit,
+            offset,
+            end,
+            tag: None,
+            lock: None,
+            groups: Vec::new(),
+            order_by: Vec::new(),
+            group_by: Vec::new(),
+            having: Vec::new(),
+            windows: Vec::new(),
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    pub fn group_by(mut self, group_by: Vec<&'a str>) -> Self {
+        self.group_by = group_by;
+        self
+    }
+
+    pub fn having(mut self, having: Vec<Condition<'a>>) -> Self {
+        self.having = having;
+        self
+    }
+
+    pub fn windows(mut self, windows: Vec<(&'a str, WindowSpec<'a>)>) -> Self {
+        self.windows = windows;
+        self
+    }
+
+    pub fn order_by(mut self, order_by: OrderBy<'a>) -> Self {
+        self.order_by = order_by
+            .into_iter()
+            .map(|(column, direction)| OrderTerm::Column(column, direction))
+            .collect();
+        self
+    }
+
+    /// same as order_by, but terms can bind values ex.: `OrderTerm::ByArrayPosition`
+    pub fn order_terms(mut self, order_terms: Vec<OrderTerm<'a>>) -> Self {
+        self.order_by = order_terms;
+        self
+    }
+
+    pub fn groups(mut self, groups: Vec<ConditionGroup<'a>>) -> Self {
+        self.groups = groups;
+        self
+    }
+
+    pub fn lock(mut self, locking: Locking, of: Vec<&'a str>) -> Self {
+        self.lock = Some((locking, of));
+        self
+    }
+
+    pub fn tag(mut self, tag: BTreeMap<&'a str, &'a str>) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// DEBUGGING AID ONLY, built sql with bound values of conditions, HAVING, LIMIT and OFFSET inlined as literals,
+    /// base query is kept as it is with its placeholders, see `Expression::preview_sql`
+    pub fn preview_sql(self) -> String {
+        let preview = self.expression().preview_sql();
+
+        match self.base_query {
+            BaseQuery::Sql(sql) => format!("{0}{1}", sql, preview),
+            BaseQuery::QueryBuilder(query) => format!("{0}{1}", query.sql(), preview),
+        }
+    }
+
+    /// number of parameters of built query without building it, including ones of base query
+    /// postgres allows at most 65535
+    pub fn param_count(&self) -> usize {
+        self.base_param_count() + self.bound_values().len()
+    }
+
+    fn base_param_count(&self) -> usize {
+        match &self.base_query {
+            BaseQuery::Sql(sql) => max_placeholder(sql),
+            BaseQuery::QueryBuilder(query) => max_placeholder(query.sql()),
+        }
+    }
+
+    /// values bound by conditions, groups, HAVING, ORDER BY, LIMIT and OFFSET in order, without ones of base query
+    pub fn bound_values(&self) -> Vec<SqlValue> {
+        self.expression().bound_values()
+    }
+
+    /// built query as expression which binds its values again where it is pushed ex.: correlated subquery of
+    /// `UpdColumnType::Subquery`, error if base query has bound values as they can not be bound again
+    pub fn into_expression(self) -> Result<Expression, ConditionError> {
+        let expression = self.expression();
+
+        let base = match self.base_query {
+            BaseQuery::Sql(sql) => sql.to_string(),
+            BaseQuery::QueryBuilder(query) => match bind_count(query) {
+                (query, 0) => query.into_sql(),
+                _ => return Err(ConditionError::BoundBaseQuery),
+            },
+        };
+
+        Ok(Expression::new().sql(base).expression(expression))
+    }
+
+    /// same as build, but returns error instead of silently dropping invalid conditions
+    pub fn try_build(self) -> Result<QueryBuilder<'a, Postgres>, ConditionError> {
+        let grouped = self.groups.iter().flat_map(ConditionGroup::leaves);
+
+        for cond in self.conditions.iter().chain(grouped).chain(self.having.iter()) {
+            if cond.expression.is_some() {
+                continue;
+            }
+
+            if let "BETWEEN" | "NOT BETWEEN" = cond.eq_opr.to_uppercase().as_str() {
+                if cond.value_r.is_none() {
+                    return Err(ConditionError::MissingRangeBound {
+                        column: cond.column.to_string(),
+                    });
+                }
+            }
+        }
+
+        Ok(self.build())
+    }
+
+    pub fn build(self) -> QueryBuilder<'a, Postgres> {
+        let expression = self.expression();
+
+        let query: QueryBuilder<'_, Postgres> = match self.base_query {
+            BaseQuery::Sql(base_sql) => QueryBuilder::new(base_sql),
+            BaseQuery::QueryBuilder(query_builder) => query_builder,
+        };
+
+        push_expression(expression, query)
+    }
+
+    /// everything after base query, values of conditions, HAVING, ORDER BY, LIMIT and OFFSET are parts of it
+    fn expression(&self) -> Expression {
+        let mut items: Vec<ConditionGroup<'a>> = self.conditions.iter().cloned().map(ConditionGroup::Leaf).collect();
+        items.extend(self.groups.iter().cloned());
+
+        let ws = self.whitespace;
+        let nl = ws.newline();
+
+        let mut expression = Self::items_expression(&items, Some("WHERE"), ws);
+
+        if !self.group_by.is_empty() {
+            expression = expression.sql(format!("{}GROUP BY {}", nl, self.group_by.join(", ")));
+        }
+
+        let having: Vec<ConditionGroup<'a>> = self.having.iter().cloned().map(ConditionGroup::Leaf).collect();
+        expression = expression.expression(Self::items_expression(&having, Some("HAVING"), ws));
+
+        if !self.windows.is_empty() {
+            expression = expression.sql(format!("{}{}", nl, window_clause(&self.windows)));
+        }
+
+        if let Some(middle_sql) = self.middle {
+            expression = expression.sql(format!("{}{}", nl, middle_sql));
+        }
+
+        for (index, term) in self.order_by.iter().enumerate() {
+            if index == 0 {
+                expression = expression.sql(format!("{}ORDER BY ", nl));
+            } else {
+                expression = expression.sql(", ");
+            }
+            expression = expression.expression(term.expression());
+        }
+
+        if let Some(limit) = self.limit {
+            expression = expression.sql(format!("{}LIMIT ", nl)).value(limit);
+        }
+
+        if let Some(offset) = self.offset {
+            expression = expression.sql(format!("{}OFFSET ", nl)).value(offset);
+        }
+
+        if let Some((locking, of)) = &self.lock {
+            expression = expression.sql(format!("{}{}", nl, locking.as_sql()));
+
+            if !of.is_empty() {
+                expression = expression.sql(format!(" OF {}", of.join(", ")));
+            }
+        }
+
+        if let Some(ending) = self.end {
+            expression = expression.sql(format!("{}{}", nl, ending));
+        }
+
+        if let Some(tag) = &self.tag {
+            expression = expression.sql(format!("{}{}", nl, tag_comment(tag)));
+        }
+
+        expression
+    }
+
+    /// renders items chained by their chain operators, chain operator of first rendered item is ignored
+    /// top level items start with keyword (WHERE or HAVING) and go on separate lines, group items (no keyword) are inline
+    fn items_expression(items: &[ConditionGroup<'a>], keyword: Option<&str>, ws: WhitespaceStyle) -> Expression {
+        let mut expression = Expression::new();
+
+        for (index, item) in ConditionGroup::rendered(items).into_iter().enumerate() {
+            if index == 0 {
+                if let Some(keyword) = keyword {
+                    expression = expression.sql(format!("{0}{1}{2}", ws.newline(), keyword, ws.indent()));
+                }
+            } else if let Some(chain_opr) = item.chain_opr() {
+                if keyword.is_some() {
+                    expression = expression.sql(format!("{0}{1} ", ws.indent(), chain_opr));
+                } else {
+                    expression = expression.sql(format!(" {0} ", chain_opr));
+                }
+            }
+
+            expression = match item {
+                ConditionGroup::Leaf(cond) => match cond.to_expression() {
+                    Some(condition) => expression.expression(condition),
+                    None => expression,
+                },
+                ConditionGroup::Group { conditions, .. } => expression
+                    .sql("(")
+                    .expression(Self::items_expression(conditions, None, ws))
+                    .sql(")"),
+                ConditionGroup::Not { conditions, .. } => expression
+                    .sql("NOT (")
+                    .expression(Self::items_expression(conditions, None, ws))
+                    .sql(")"),
+            };
+        }
+
+        expression
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::NaiveDate;
+    use serde_json::Value;
+
+    use crate::condition::{Condition, ConditionBuilder, ConditionError, ConditionGroup, LikeMode, Locking, OrderTerm, SortDirection};
+    use crate::general::BaseQuery;
+    use crate::{CaseExpr, Expression, ExpressionPart, SelectBuilder, SqlValue, WhitespaceStyle};
+
+    #[test]
+    fn between_with_where() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(
+                None,
+                "test_col",
+                "BETWEEN",
+                5.into(),
+                Some(24.into()),
+            ),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col BETWEEN $1 AND $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn single_condition_with_where() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(
+                None,
+                "test_col",
+                "LIKE",
+                "sample".into(),
+                None,
+            ),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col LIKE $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn single_condition_with_in_operator() {
+        let mut conditions: Vec<Condition> = Vec::new();
+        
+        let list: Vec<&str> = vec!["ab", "cd", "ef"];
+
+        conditions.push(Condition::new(
+            None,
+            "test_col",
+            "IN",
+            list.into(),
+            None,
+        ));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col IN ($1, $2, $3)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn single_condition_without_where() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(
+                Some(""),
+                "test_col",
+                "LIKE",
+                "sample".into(),
+                None,
+            ),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col LIKE $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn single_condition_with_chain_operand() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(
+                Some("AND"),
+                "test_col",
+                "LIKE",
+                "sample".into(),
+                None,
+            ),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col LIKE $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn empty_condition_builder() {
+        let conditions: Vec<Condition> = Vec::new();
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "");
+    }
+
+    #[test]
+    fn multiple_conditions() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(
+                Some("AND"),
+                "test_col",
+                "LIKE",
+                "sample".into(),
+                None,
+            ),
+            Condition::new(
+                Some("OR"),
+                "test_col2",
+                "=",
+                5.into(),
+                None,
+            ),
+            // This condition will be ignored because there is no chain operator
+            Condition::new(
+                None,
+                "other_col",
+                "=",
+                7.into(),
+                None
+            ),
+        ];
+
+        let order_by = "ORDER BY\n    id DESC";
+
+        let test_query = ConditionBuilder::new(
+            BaseQuery::Sql(""),
+            &conditions,
+            Some(order_by),
+            Some(10),
+            Some(0),
+            None,
+        );
+
+        let result = r#"
+WHERE
+    test_col LIKE $1
     OR test_col2 = $2
 ORDER BY
     id DESC
@@ -354,4 +1416,1025 @@ OFFSET $4"#;
 
         assert_eq!(test_query.build().into_sql(), result);
     }
+
+    #[test]
+    fn case_expression_condition() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let case = CaseExpr::new(vec![("premium".into(), 2.into())], Some(1.into()));
+
+        conditions.push(Condition::new(None, "active", "=", true.into(), None));
+        conditions.push(Condition::expression(Some("AND"), case, ">", 1.into()));
+
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    active = $1\n    AND (CASE WHEN premium THEN $2 ELSE $3 END) > $4";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn expression_condition_as_first() {
+        let conditions: Vec<Condition> = vec![
+            Condition::from_expression(
+                Some("AND"),
+                "email",
+                "=",
+                Expression::new().sql("lower(email) = lower(").value("a@b.com").sql(")"),
+            ),
+        ];
+
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    lower(email) = lower($1)");
+    }
+
+    #[test]
+    fn not_between_with_where() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "test_col", "NOT BETWEEN", 5.into(), Some(24.into()))];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    test_col NOT BETWEEN $1 AND $2");
+    }
+
+    #[test]
+    fn try_build_between_without_value_r() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "id", "=", 5.into(), None),
+            Condition::new(Some("AND"), "test_col", "BETWEEN", 5.into(), None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let error = ConditionError::MissingRangeBound { column: "test_col".to_string() };
+
+        assert_eq!(test_query.try_build().err(), Some(error));
+    }
+
+    #[test]
+    fn try_build_valid_conditions() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "test_col", "BETWEEN", 5.into(), Some(24.into()))];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    test_col BETWEEN $1 AND $2";
+
+        assert_eq!(test_query.try_build().unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn conditions_with_tag() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql("SELECT * FROM users"), &conditions, None, None, None, None)
+                .tag(BTreeMap::from([("app", "svc"), ("endpoint", "/users")]));
+
+        let result = "SELECT * FROM users\nWHERE\n    id = $1\n/* app=svc,endpoint=/users */";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn conditions_with_locking() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "users.id", "=", 5.into(), None)];
+        let test_query = ConditionBuilder::new(
+            BaseQuery::Sql("SELECT * FROM users JOIN roles ON roles.id = users.role_id"),
+            &conditions,
+            None,
+            Some(1),
+            None,
+            None,
+        )
+        .lock(Locking::NoKeyUpdate, vec!["users"]);
+
+        let result = "SELECT * FROM users JOIN roles ON roles.id = users.role_id\nWHERE\n    users.id = $1\nLIMIT $2\nFOR NO KEY UPDATE OF users";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn conditions_with_key_share_locking() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None)
+                .lock(Locking::KeyShare, Vec::new());
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    id = $1\nFOR KEY SHARE");
+    }
+
+    #[test]
+    fn function_compare_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::func_cmp(None, "round", "amount", vec!["2"], "=", Value::from(10.5).into()),
+            Condition::func_cmp(Some("AND"), "lower", "name", Vec::new(), "LIKE", "abc%".into()),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    round(amount, 2) = $1\n    AND lower(name) LIKE $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn array_empty_conditions() {
+        let conditions: Vec<Condition> = vec![
+            Condition::array_not_empty(None, "tags"),
+            Condition::array_empty(Some("AND"), "labels"),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    cardinality(tags) > 0\n    AND cardinality(labels) = 0";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn overlaps_condition() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let start = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let end = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+
+        conditions.push(Condition::new(None, "room_id", "=", 3.into(), None));
+        conditions.push(Condition::overlaps(Some("AND"), ("starts_on", "ends_on"), (start.into(), end.into())));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    room_id = $1\n    AND (starts_on, ends_on) OVERLAPS ($2, $3)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn case_insensitive_equality() {
+        let conditions: Vec<Condition> = vec![
+            Condition::ci_eq(None, "email", "Sample@Mail.com".into()),
+            Condition::new(Some("AND"), "active", "=", true.into(), None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    lower(email) = lower($1)\n    AND active = $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn greatest_and_least_conditions() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let since = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+
+        conditions.push(Condition::greatest(None, vec!["updated_at", "created_at"], ">", since.into()).unwrap());
+        conditions.push(Condition::least(Some("AND"), vec!["price", "discount_price"], "<=", 100.into()).unwrap());
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    GREATEST(updated_at, created_at) > $1\n    AND LEAST(price, discount_price) <= $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn greatest_without_columns() {
+        assert!(matches!(Condition::greatest(None, Vec::new(), ">", 1.into()), Err(ConditionError::EmptyColumns)));
+        assert!(matches!(Condition::least(None, Vec::new(), "<", 1.into()), Err(ConditionError::EmptyColumns)));
+    }
+
+    #[test]
+    fn in_null_safe_condition() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let with_null: Vec<Value> = vec!["a".into(), Value::Null, "b".into()];
+
+        conditions.push(Condition::in_null_safe(None, "category", with_null.into()));
+        conditions.push(Condition::in_null_safe(Some("AND"), "status", vec![1, 2].into()));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    (category IN ($1, $2) OR category IS NULL)\n    AND status IN ($3, $4)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn in_null_safe_only_null() {
+        let conditions: Vec<Condition> = vec![Condition::in_null_safe(None, "category", vec![Value::Null].into())];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    category IS NULL");
+    }
+
+    #[test]
+    fn in_null_safe_scalar() {
+        let conditions: Vec<Condition> = vec![
+            Condition::in_null_safe(None, "category", "a".into()),
+            Condition::in_null_safe(Some("AND"), "status", Value::Null.into()),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    category = $1\n    AND status IS NULL");
+    }
+
+    #[test]
+    fn contains_substring_condition() {
+        let conditions: Vec<Condition> = vec![Condition::contains_substring(None, "title", "100%_off".into())];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    strpos(title, $1) > 0");
+    }
+
+    #[test]
+    fn like_patterns_condition() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let patterns: Vec<String> = vec!["%sale%".to_string(), "promo%".to_string(), "%deal".to_string()];
+
+        conditions.push(Condition::new(None, "active", "=", true.into(), None));
+        conditions.push(Condition::like_patterns(Some("AND"), "title", patterns, true));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    active = $1\n    AND (title ILIKE $2 OR title ILIKE $3 OR title ILIKE $4)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    #[cfg(feature = "postgis")]
+    fn st_dwithin_condition() {
+        let conditions: Vec<Condition> = vec![Condition::st_dwithin(None, "location", "SRID=4326;POINT(13.4 52.5)".into(), 500.into())];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    ST_DWithin(location, ST_GeogFromText($1), $2)");
+    }
+
+    #[test]
+    fn within_pct_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "sku", "=", "A-1".into(), None),
+            Condition::within_pct(Some("AND"), "price", 200.into(), 10.0).unwrap(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    sku = $1\n    AND price BETWEEN SYMMETRIC $2 * 0.9 AND $2 * 1.1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn within_pct_not_finite() {
+        assert!(matches!(Condition::within_pct(None, "price", 200.into(), f64::INFINITY), Err(ConditionError::InvalidPercent(_))));
+        assert!(matches!(Condition::within_pct(None, "price", 200.into(), f64::NAN), Err(ConditionError::InvalidPercent(_))));
+    }
+
+    #[test]
+    fn eq_or_null_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::eq_or_null(None, "parent_id", Some(7.into())),
+            Condition::eq_or_null(Some("AND"), "owner_id", None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    parent_id = $1\n    AND owner_id IS NULL");
+    }
+
+    #[test]
+    fn enum_any_condition() {
+        let statuses: Vec<String> = vec!["active".to_string(), "on \"hold\"".to_string()];
+        let condition = Condition::enum_any(None, "status", "status_enum", statuses);
+
+        assert!(matches!(
+            &condition.expression.as_ref().unwrap().parts[1],
+            ExpressionPart::Value(SqlValue::GenericValue(Value::String(v))) if v == r#"{"active","on \"hold\""}"#
+        ));
+
+        let conditions: Vec<Condition> = vec![condition];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    status = ANY($1::status_enum[])");
+    }
+
+    #[test]
+    fn trigram_similar_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::trigram_similar(None, "name", "jon smith".into(), None),
+            Condition::trigram_similar(Some("OR"), "alias", "jon smith".into(), Some(0.4)),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    name % $1\n    OR similarity(alias, $2) > $3";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn extract_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::extract(None, "year", "created_at", "=", 2024.into()).unwrap(),
+            Condition::extract(Some("AND"), "DOW", "created_at", "=", 6.into()).unwrap(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    EXTRACT(YEAR FROM created_at) = $1\n    AND EXTRACT(DOW FROM created_at) = $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+        assert_eq!(
+            Condition::extract(None, "year from now()) --", "created_at", "=", 2024.into()).err(),
+            Some(ConditionError::InvalidExtractField("YEAR FROM NOW()) --".to_string()))
+        );
+    }
+
+    #[test]
+    fn json_array_contains_condition() {
+        let condition = Condition::json_array_contains(None, "tags", "rust".into());
+
+        assert!(matches!(
+            &condition.expression.as_ref().unwrap().parts[1],
+            ExpressionPart::Value(SqlValue::GenericValue(Value::String(v))) if v == r#"["rust"]"#
+        ));
+
+        let conditions: Vec<Condition> = vec![condition];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    tags @> $1::jsonb");
+    }
+
+    #[test]
+    fn has_all_flags_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "active", "=", true.into(), None),
+            Condition::has_all_flags(Some("AND"), "permissions", 6.into()),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    active = $1\n    AND (permissions & $2) = $2");
+    }
+
+    #[test]
+    fn has_any_flags_condition() {
+        let conditions: Vec<Condition> = vec![Condition::has_any_flags(None, "permissions", 6.into())];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    (permissions & $1) <> 0");
+    }
+
+    #[test]
+    fn array_intersection_at_least_condition() {
+        let tags: Vec<Value> = vec!["rust".into(), "sql".into(), "web".into()];
+        let condition = Condition::array_intersection_at_least(None, "tags", tags, 2);
+
+        assert!(matches!(
+            &condition.expression.as_ref().unwrap().parts[1],
+            ExpressionPart::Value(SqlValue::GenericValue(Value::String(v))) if v == r#"{"rust","sql","web"}"#
+        ));
+
+        let conditions: Vec<Condition> = vec![condition];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    cardinality(array(SELECT unnest(tags)::text INTERSECT SELECT unnest($1::text[]))) >= $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn like_modes() {
+        let conditions: Vec<Condition> = vec![
+            Condition::like(None, "name", "jo", LikeMode::StartsWith),
+            Condition::like(Some("OR"), "name", "son", LikeMode::EndsWith),
+            Condition::like(Some("OR"), "code", "A_1%", LikeMode::Raw),
+            Condition::new(Some("OR"), "title", "LIKE", "sale".into(), None),
+            Condition::new(Some("OR"), "note", "NOT ILIKE", "spam".into(), None),
+        ];
+
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+        let preview_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+        let result = "\nWHERE\n    name LIKE $1\n    OR name LIKE $2\n    OR code LIKE $3\n    OR title LIKE $4\n    OR note NOT ILIKE $5";
+        let preview = "\nWHERE\n    name LIKE 'jo%'\n    OR name LIKE '%son'\n    OR code LIKE 'A_1%'\n    OR title LIKE '%sale%'\n    OR note NOT ILIKE '%spam%'";
+
+        assert_eq!(test_query.build().into_sql(), result);
+        assert_eq!(preview_query.preview_sql(), preview);
+    }
+
+    #[test]
+    fn not_in_with_where() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let statuses: Vec<&str> = vec!["deleted", "banned"];
+        let ids: Vec<i64> = vec![1, 2, 3];
+
+        conditions.push(Condition::new(None, "status", "NOT IN", statuses.into(), None));
+        conditions.push(Condition::new(Some("AND"), "id", "not in", ids.into(), None));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    status NOT IN ($1, $2)\n    AND id not in ($3, $4, $5)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn not_in_chained() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let ids: Vec<i64> = vec![4, 5];
+        let statuses: Vec<&str> = vec!["deleted"];
+
+        conditions.push(Condition::new(None, "id", "NOT IN", ids.into(), None));
+        conditions.push(Condition::new(Some("OR"), "status", "NOT IN", statuses.into(), None));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    id NOT IN ($1, $2)\n    OR status NOT IN ($3)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn prefix_range_condition() {
+        let condition = Condition::prefix_range(None, "name", "abc");
+        let bounds: Vec<String> = condition
+            .expression
+            .as_ref()
+            .unwrap()
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                ExpressionPart::Value(SqlValue::GenericValue(Value::String(v))) => Some(v.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let conditions: Vec<Condition> = vec![condition];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(bounds, vec!["abc", "abd"]);
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    (name >= $1 AND name < $2)");
+    }
+
+    #[test]
+    fn prefix_range_max_char() {
+        let condition = Condition::prefix_range(None, "name", &format!("a{0}", char::MAX));
+        let only_max = Condition::prefix_range(None, "name", &char::MAX.to_string());
+
+        assert!(matches!(
+            &condition.expression.as_ref().unwrap().parts[3],
+            ExpressionPart::Value(SqlValue::GenericValue(Value::String(v))) if v == "b"
+        ));
+        assert_eq!(only_max.expression.unwrap().parts.len(), 2);
+    }
+
+    #[test]
+    fn empty_in_list() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let empty: Vec<i64> = Vec::new();
+
+        conditions.push(Condition::new(None, "id", "IN", empty.into(), None));
+        conditions.push(Condition::new(Some("OR"), "org_id", "=", 5.into(), None));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    FALSE\n    OR org_id = $1");
+    }
+
+    #[test]
+    fn empty_not_in_list() {
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        let empty: Vec<&str> = Vec::new();
+
+        conditions.push(Condition::new(None, "org_id", "=", 5.into(), None));
+        conditions.push(Condition::new(Some("AND"), "status", "NOT IN", empty.into(), None));
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    org_id = $1\n    AND TRUE");
+    }
+
+    #[test]
+    fn is_null_conditions() {
+        let conditions: Vec<Condition> = vec![
+            Condition::is_null(None, "deleted_at"),
+            Condition::is_not_null(Some("AND"), "verified_at"),
+            Condition::new(Some("OR"), "archived_at", "is not null", Value::Null.into(), None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = test_query.build().into_sql();
+
+        assert_eq!(result, "\nWHERE\n    deleted_at IS NULL\n    AND verified_at IS NOT NULL\n    OR archived_at is not null");
+        assert!(!result.contains('$'));
+    }
+
+    #[test]
+    fn now_between_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "published", "=", true.into(), None),
+            Condition::now_between(Some("AND"), "starts_at", "ends_at"),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    published = $1\n    AND now() BETWEEN starts_at AND ends_at");
+    }
+
+    #[test]
+    fn grouped_conditions() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let groups: Vec<ConditionGroup> = vec![
+            ConditionGroup::Group {
+                chain_opr: Some("AND"),
+                conditions: vec![
+                    Condition::new(None, "status", "=", "open".into(), None).into(),
+                    ConditionGroup::Group {
+                        chain_opr: Some("OR"),
+                        conditions: vec![
+                            Condition::new(Some("AND"), "status", "=", "closed".into(), None).into(),
+                            Condition::is_null(Some("AND"), "closed_by").into(),
+                        ],
+                    },
+                ],
+            },
+            Condition::new(Some("AND"), "priority", ">", 2.into(), None).into(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None).groups(groups);
+
+        let result = "\nWHERE\n    org_id = $1\n    AND (status = $2 OR (status = $3 AND closed_by IS NULL))\n    AND priority > $4";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn group_first_after_where() {
+        let conditions: Vec<Condition> = Vec::new();
+        let groups: Vec<ConditionGroup> = vec![
+            ConditionGroup::Group {
+                chain_opr: Some("AND"),
+                conditions: vec![
+                    Condition::new(Some("OR"), "a", "=", 1.into(), None).into(),
+                    Condition::new(Some("OR"), "b", "=", 2.into(), None).into(),
+                ],
+            },
+            Condition::new(Some("AND"), "c", "=", 3.into(), None).into(),
+            ConditionGroup::Group { chain_opr: Some("AND"), conditions: Vec::new() },
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM t"), &conditions, None, None, None, None).groups(groups);
+
+        let result = "SELECT *\nFROM t\nWHERE\n    (a = $1 OR b = $2)\n    AND c = $3";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    #[cfg(feature = "uuid")]
+    fn uuid_in_list() {
+        let ids: Vec<uuid::Uuid> = vec![uuid::Uuid::nil(), uuid::Uuid::from_u128(1)];
+
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "id", "IN", ids.clone().into(), None),
+            Condition::new(Some("OR"), "owner_id", "=", ids[0].into(), None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    id IN ($1, $2)\n    OR owner_id = $3");
+    }
+
+    #[test]
+    fn json_typeof_condition() {
+        let conditions: Vec<Condition> = vec![
+            Condition::json_typeof(None, "data", vec!["field"], "string"),
+            Condition::json_typeof(Some("OR"), "data", vec!["owner's", "id"], "number"),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    jsonb_typeof(data->'field') = $1\n    OR jsonb_typeof(data->'owner''s'->'id') = $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn scalar_subquery_comparison() {
+        let subquery = Expression::new()
+            .sql("SELECT avg(score) FROM results WHERE season = ")
+            .value(2024);
+
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "team_id", "=", 5.into(), None),
+            Condition::cmp_subquery(Some("AND"), "score", ">", subquery),
+            Condition::new(Some("AND"), "season", "=", 2024.into(), None),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    team_id = $1\n    AND score > (SELECT avg(score) FROM results WHERE season = $2)\n    AND season = $3";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn descendant_of_root() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "active", "=", true.into(), None),
+            Condition::descendant_of(Some("AND"), "category_id", "categories", "id", "parent_id", 5.into()),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    active = $1\n    AND category_id IN (WITH RECURSIVE descendants AS (SELECT id FROM categories WHERE parent_id = $2 UNION ALL SELECT t.id FROM categories t JOIN descendants d ON t.parent_id = d.id) SELECT id FROM descendants)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn near_time_window() {
+        let center = chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().and_hms_opt(10, 30, 0).unwrap();
+
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "device_id", "=", 5.into(), None),
+            Condition::near_time(Some("AND"), "happened_at", center, chrono::Duration::minutes(5)),
+            Condition::near_time(Some("OR"), "reported_at", center, chrono::Duration::milliseconds(-1500)),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    device_id = $1\n    AND happened_at BETWEEN $2 - interval '300 seconds' AND $2 + interval '300 seconds'\n    OR reported_at BETWEEN $3 - interval '1500 milliseconds' AND $3 + interval '1500 milliseconds'";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn order_by_single_column() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM users"), &conditions, None, Some(10), None, None)
+            .order_by(vec![("id", SortDirection::Desc)]);
+
+        let result = "SELECT *\nFROM users\nWHERE\n    org_id = $1\nORDER BY id DESC\nLIMIT $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn order_by_multiple_columns() {
+        let conditions: Vec<Condition> = Vec::new();
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM tasks"), &conditions, None, Some(10), Some(20), None)
+            .order_by(vec![
+                ("due_at", SortDirection::AscNullsFirst),
+                ("priority", SortDirection::DescNullsLast),
+                ("id", SortDirection::Asc),
+            ]);
+
+        let result = "SELECT *\nFROM tasks\nORDER BY due_at ASC NULLS FIRST, priority DESC NULLS LAST, id ASC\nLIMIT $1\nOFFSET $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn order_by_empty() {
+        let conditions: Vec<Condition> = Vec::new();
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM tasks"), &conditions, None, Some(10), None, None)
+            .order_by(Vec::new());
+
+        assert_eq!(test_query.build().into_sql(), "SELECT *\nFROM tasks\nLIMIT $1");
+    }
+
+    #[test]
+    fn group_by_only() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "year", "=", 2024.into(), None)];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT region, count(*)\nFROM sales"), &conditions, None, None, None, None)
+            .group_by(vec!["region"]);
+
+        let result = "SELECT region, count(*)\nFROM sales\nWHERE\n    year = $1\nGROUP BY region";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn group_by_case_insensitive() {
+        let conditions: Vec<Condition> = Vec::new();
+        let collated = crate::collate("name", "und-x-icu");
+        let lower_query = ConditionBuilder::new(BaseQuery::Sql("SELECT lower(name), count(*)\nFROM tags"), &conditions, None, None, None, None)
+            .group_by(vec!["lower(name)"]);
+        let collated_query = ConditionBuilder::new(BaseQuery::Sql("SELECT min(name), count(*)\nFROM tags"), &conditions, None, None, None, None)
+            .group_by(vec![&collated]);
+
+        assert_eq!(lower_query.build().into_sql(), "SELECT lower(name), count(*)\nFROM tags\nGROUP BY lower(name)");
+        assert_eq!(collated_query.build().into_sql(), "SELECT min(name), count(*)\nFROM tags\nGROUP BY name COLLATE \"und-x-icu\"");
+    }
+
+    #[test]
+    fn group_by_with_having() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "year", "=", 2024.into(), None)];
+        let having: Vec<Condition> = vec![
+            Condition::from_expression(None, "amount", ">", Expression::new().sql("sum(amount) > ").value(1000)),
+            Condition::new(Some("AND"), "count(*)", ">=", 3.into(), None),
+        ];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT region, month, sum(amount)\nFROM sales"), &conditions, None, Some(10), None, None)
+            .group_by(vec!["region", "month"])
+            .having(having)
+            .order_by(vec![("region", SortDirection::Asc)]);
+
+        let result = "SELECT region, month, sum(amount)\nFROM sales\nWHERE\n    year = $1\nGROUP BY region, month\nHAVING\n    sum(amount) > $2\n    AND count(*) >= $3\nORDER BY region ASC\nLIMIT $4";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn having_aggregate_without_alias() {
+        let conditions: Vec<Condition> = Vec::new();
+        let having: Vec<Condition> = vec![Condition::new(None, "sum(amount)", ">", 1000.into(), None)];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT customer_id, sum(amount) AS total\nFROM orders"), &conditions, None, None, None, None)
+            .group_by(vec!["customer_id"])
+            .having(having);
+
+        let result = "SELECT customer_id, sum(amount) AS total\nFROM orders\nGROUP BY customer_id\nHAVING\n    sum(amount) > $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn whole_number_check() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "currency", "=", "JPY".into(), None),
+            Condition::is_whole(Some("AND"), "amount"),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    currency = $1\n    AND amount = floor(amount)");
+    }
+
+    #[test]
+    fn length_between_bounds() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "active", "=", true.into(), None),
+            Condition::length_between(Some("AND"), "name", 3, 64),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    active = $1\n    AND char_length(name) BETWEEN $2 AND $3");
+    }
+
+    #[test]
+    fn json_eq_with_cast() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "id", "=", 5.into(), None),
+            Condition::json_eq(Some("AND"), "data", serde_json::json!({"b": 1, "a": [true]})),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    id = $1\n    AND data = $2::jsonb");
+        assert!(matches!(
+            &conditions[1].expression.as_ref().unwrap().parts[1],
+            ExpressionPart::Value(SqlValue::GenericValue(serde_json::Value::String(v))) if v == r#"{"a":[true],"b":1}"#
+        ));
+    }
+
+    #[test]
+    fn preview_with_literals() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "name", "LIKE", "O'Brien".into(), None),
+            Condition::new(Some("AND"), "id", "IN", vec![1, 2].into(), None),
+            Condition::new(Some("AND"), "created_at", "BETWEEN", NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().into(), Some(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap().into())),
+            Condition::new(Some("AND"), "deleted_at", "IS NULL", Value::Null.into(), None),
+        ];
+        let groups: Vec<ConditionGroup> = vec![ConditionGroup::Group {
+            chain_opr: Some("AND"),
+            conditions: vec![
+                Condition::new(None, "active", "=", true.into(), None).into(),
+                Condition::new(Some("OR"), "score", ">", Value::from(7.5).into(), None).into(),
+            ],
+        }];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM users"), &conditions, None, Some(10), None, None)
+            .groups(groups);
+
+        let result = "SELECT *\nFROM users\nWHERE\n    name LIKE '%O''Brien%'\n    AND id IN (1, 2)\n    AND created_at BETWEEN '2024-01-01' AND '2024-02-01'\n    AND deleted_at IS NULL\n    AND (active = TRUE OR score > 7.5)\nLIMIT 10";
+
+        assert_eq!(test_query.preview_sql(), result);
+    }
+
+    #[test]
+    fn preview_keeps_placeholder_in_literal() {
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("org_id", 5),
+            Condition::from_expression(Some("AND"), "note", "=", Expression::new().sql("note <> '$1' /* $2 */")),
+        ];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT '$1' AS label\nFROM users"), &conditions, None, Some(10), None, None);
+
+        let result = "SELECT '$1' AS label\nFROM users\nWHERE\n    org_id = 5\n    AND note <> '$1' /* $2 */\nLIMIT 10";
+
+        assert_eq!(test_query.preview_sql(), result);
+        assert_eq!(crate::preview_sql("SELECT '$1', $q$ $1 $q$, E'\\' $1', $1 -- $1", 0, &[7.into()]), "SELECT '$1', $q$ $1 $q$, E'\\' $1', 7 -- $1");
+        assert_eq!(crate::max_placeholder("SELECT '$2' /* $3 */, \"$4\", $1"), 1);
+    }
+
+    #[test]
+    fn param_count_mixed_conditions() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "status", "IN", vec!["new", "paid", "sent"].into(), None),
+            Condition::new(Some("AND"), "total", "BETWEEN", 10.into(), Some(100.into())),
+            Condition::new(Some("AND"), "deleted_at", "IS NULL", Value::Null.into(), None),
+            Condition::new(Some("AND"), "note", "=", Value::Null.into(), None),
+            Condition::within_pct(Some("AND"), "price", 50.into(), 10.0).unwrap(),
+            Condition::new(Some("AND"), "score", "BETWEEN", 1.into(), None),
+        ];
+        let base = Expression::new().sql("SELECT *\nFROM orders WHERE org_id = ").value(5);
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(crate::push_expression(base, sqlx::QueryBuilder::new(""))), &conditions, None, Some(10), Some(20), None);
+
+        let param_count = test_query.param_count();
+        let sql = test_query.build().into_sql();
+
+        assert_eq!(param_count, 9);
+        assert_eq!(crate::max_placeholder(&sql), param_count);
+    }
+
+    #[test]
+    fn negated_group() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let groups: Vec<ConditionGroup> = vec![
+            ConditionGroup::Not {
+                chain_opr: Some("AND"),
+                conditions: vec![
+                    Condition::new(None, "a", "=", 1.into(), None).into(),
+                    Condition::new(Some("OR"), "b", "IN", vec![2, 3].into(), None).into(),
+                ],
+            },
+            Condition::new(Some("AND"), "c", "=", 4.into(), None).into(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None).groups(groups);
+
+        let result = "\nWHERE\n    org_id = $1\n    AND NOT (a = $2 OR b IN ($3, $4))\n    AND c = $5";
+
+        assert_eq!(test_query.param_count(), 5);
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn exists_by_composite_key() {
+        let key = vec![("org_id", 5.into()), ("user_id", 7.into())];
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "active", "=", true.into(), None),
+            Condition::exists_by_key(Some("AND"), "memberships", key.clone()).unwrap(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        let result = "\nWHERE\n    active = $1\n    AND EXISTS (SELECT 1 FROM memberships WHERE org_id = $2 AND user_id = $3)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+        assert_eq!(
+            crate::select_exists_by_key("memberships", key).unwrap().into_sql(),
+            "SELECT EXISTS (SELECT 1 FROM memberships WHERE org_id = $1 AND user_id = $2)"
+        );
+    }
+
+    #[test]
+    fn exists_by_empty_key() {
+        assert!(matches!(Condition::exists_by_key(None, "memberships", Vec::new()), Err(ConditionError::EmptyColumns)));
+        assert!(matches!(crate::select_exists_by_key("memberships", Vec::new()), Err(ConditionError::EmptyColumns)));
+    }
+
+    #[test]
+    fn shorthand_constructors() {
+        let pairs: Vec<(Condition, Condition)> = vec![
+            (Condition::eq("id", 5), Condition::new(None, "id", "=", 5.into(), None)),
+            (Condition::and_eq("name", "a"), Condition::new(Some("AND"), "name", "=", "a".into(), None)),
+            (Condition::or_eq("active", true), Condition::new(Some("OR"), "active", "=", true.into(), None)),
+            (Condition::gt("score", 1), Condition::new(None, "score", ">", 1.into(), None)),
+            (Condition::and_gt("score", 1), Condition::new(Some("AND"), "score", ">", 1.into(), None)),
+            (Condition::or_gt("score", 1), Condition::new(Some("OR"), "score", ">", 1.into(), None)),
+            (Condition::lt("score", 2), Condition::new(None, "score", "<", 2.into(), None)),
+            (Condition::and_lt("score", 2), Condition::new(Some("AND"), "score", "<", 2.into(), None)),
+            (Condition::or_lt("score", 2), Condition::new(Some("OR"), "score", "<", 2.into(), None)),
+            (Condition::gte("score", 3), Condition::new(None, "score", ">=", 3.into(), None)),
+            (Condition::and_gte("score", 3), Condition::new(Some("AND"), "score", ">=", 3.into(), None)),
+            (Condition::or_gte("score", 3), Condition::new(Some("OR"), "score", ">=", 3.into(), None)),
+            (Condition::lte("score", 4), Condition::new(None, "score", "<=", 4.into(), None)),
+            (Condition::and_lte("score", 4), Condition::new(Some("AND"), "score", "<=", 4.into(), None)),
+            (Condition::or_lte("score", 4), Condition::new(Some("OR"), "score", "<=", 4.into(), None)),
+        ];
+
+        for (short, verbose) in pairs {
+            assert_eq!(format!("{short:?}"), format!("{verbose:?}"));
+        }
+    }
+
+    #[test]
+    fn shorthand_conditions_rendered() {
+        let conditions: Vec<Condition> = vec![Condition::eq("org_id", 5), Condition::and_gte("age", 18), Condition::or_eq("admin", true)];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    org_id = $1\n    AND age >= $2\n    OR admin = $3");
+    }
+
+    #[test]
+    fn whitespace_styles() {
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("org_id", 5),
+            Condition::from_expression(Some("AND"), "note", "=", Expression::new().sql("note <> 'a\n  b'")),
+        ];
+        let build = |style: WhitespaceStyle| {
+            let mut query = SelectBuilder::new("users", Vec::new())
+                .whitespace(style)
+                .with_conditions(&conditions)
+                .unwrap()
+                .group_by(vec!["org_id"]);
+            query.limit = Some(10);
+
+            query.build().into_sql()
+        };
+
+        assert_eq!(build(WhitespaceStyle::Pretty), "SELECT *\nFROM users\nWHERE\n    org_id = $1\n    AND note <> 'a\n  b'\nGROUP BY org_id\nLIMIT $2");
+        assert_eq!(build(WhitespaceStyle::Compact), "SELECT *\nFROM users\nWHERE\norg_id = $1\nAND note <> 'a\n  b'\nGROUP BY org_id\nLIMIT $2");
+        assert_eq!(build(WhitespaceStyle::SingleLine), "SELECT * FROM users WHERE org_id = $1 AND note <> 'a\n  b' GROUP BY org_id LIMIT $2");
+    }
+
+    #[test]
+    fn against_session_setting() {
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("active", true),
+            Condition::against_setting(Some("AND"), "tenant_id", "=", "app.tenant_id", Some("int")).unwrap(),
+        ];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(""), &conditions, None, None, None, None);
+
+        assert_eq!(test_query.build().into_sql(), "\nWHERE\n    active = $1\n    AND tenant_id = current_setting('app.tenant_id')::int");
+        assert_eq!(
+            Condition::against_setting(None, "tenant_id", "=", "app.tenant_id'); DROP TABLE t; --", None).err(),
+            Some(ConditionError::InvalidSetting("app.tenant_id'); DROP TABLE t; --".to_string()))
+        );
+        assert!(Condition::against_setting(None, "tenant_id", "=", "app.tenant_id", Some("int; --")).is_err());
+    }
+
+    #[test]
+    fn single_line_keeps_raw_sql() {
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("org_id", 5),
+            Condition::from_expression(Some("AND"), "note", "=", Expression::new().sql("note <> E'it\\'s\n  x' AND body <> $q$a\n  b$q$")),
+        ];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT * /* a\n  b */\nFROM t -- note\n"), &conditions, None, Some(10), None, None)
+            .whitespace(WhitespaceStyle::SingleLine);
+
+        let result = "SELECT * /* a\n  b */\nFROM t -- note\n WHERE org_id = $1 AND note <> E'it\\'s\n  x' AND body <> $q$a\n  b$q$ LIMIT $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn order_by_array_position() {
+        let ids: Vec<i64> = vec![7, 3, 5];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "IN", ids.clone().into(), None)];
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM products"), &conditions, None, Some(10), None, None)
+            .order_terms(vec![
+                OrderTerm::ByArrayPosition { array_value: ids.into(), column: "id" },
+                OrderTerm::Column("name", SortDirection::Asc),
+            ]);
+
+        let result = "SELECT *\nFROM products\nWHERE\n    id IN ($1, $2, $3)\nORDER BY array_position($4::text[], id::text), name ASC\nLIMIT $5";
+
+        assert_eq!(test_query.param_count(), 5);
+        assert!(matches!(&test_query.bound_values()[3], SqlValue::GenericValue(Value::String(v)) if v == r#"{"7","3","5"}"#));
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn order_by_empty_array_position() {
+        let ids: Vec<i64> = Vec::new();
+        let conditions: Vec<Condition> = Vec::new();
+        let test_query = ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM products"), &conditions, None, None, None, None)
+            .order_terms(vec![OrderTerm::ByArrayPosition { array_value: ids.into(), column: "id" }]);
+
+        assert!(matches!(&test_query.bound_values()[0], SqlValue::GenericValue(Value::String(v)) if v == "{}"));
+        assert_eq!(test_query.build().into_sql(), "SELECT *\nFROM products\nORDER BY array_position($1::text[], id::text)");
+    }
 }
@@ -0,0 +1,314 @@
+use std::fmt;
+
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{BaseQuery, Expression, WhitespaceStyle, bind_count, push_expression, wrap_query};
+
+/// optimization fence hint of a CTE (postgres 12+) ex.: `WITH recent AS MATERIALIZED (..)`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Materialization {
+    Materialized,
+    NotMaterialized,
+}
+
+impl Materialization {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            Materialization::Materialized => "MATERIALIZED",
+            Materialization::NotMaterialized => "NOT MATERIALIZED",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CteError {
+    /// built query with bound values after other bound values, sqlx can not merge bound values of two built queries,
+    /// continue building on the result instead ex.: `BaseQuery::QueryBuilder` into `ConditionBuilder`
+    UnmergeableValues(String),
+}
+
+impl fmt::Display for CteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CteError::UnmergeableValues(name) => write!(f, "bound values of `{name}` can not follow other bound values"),
+        }
+    }
+}
+
+impl std::error::Error for CteError {}
+
+/// query of a CTE
+pub enum CteQuery<'a> {
+    /// values are bound in place, after values of earlier CTEs
+    Expression(Expression),
+    /// built query ex.: `ConditionBuilder::build`, its bound values are kept,
+    /// so it can bind values only if no CTE before it does
+    QueryBuilder(QueryBuilder<'a, Postgres>),
+}
+
+impl<'a> From<Expression> for CteQuery<'a> {
+    fn from(value: Expression) -> Self {
+        Self::Expression(value)
+    }
+}
+
+impl<'a> From<QueryBuilder<'a, Postgres>> for CteQuery<'a> {
+    fn from(value: QueryBuilder<'a, Postgres>) -> Self {
+        Self::QueryBuilder(value)
+    }
+}
+
+/// named CTEs prepended to a query, values of CTEs are bound first
+/// ex.: `WITH recent AS (..) SELECT ..`, then `BaseQuery::QueryBuilder` into `ConditionBuilder` for outer conditions
+#[derive(Default)]
+pub struct WithBuilder<'a> {
+    /// name, query and materialization hint, postgres decides if hint is None
+    pub ctes: Vec<(&'a str, CteQuery<'a>, Option<Materialization>)>,
+    /// line layout of WITH clause, CTE queries are rendered as given, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
+}
+
+impl<'a> WithBuilder<'a> {
+    pub fn new() -> Self {
+        Self {
+            ctes: Vec::new(),
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    /// CTEs are rendered in order of adding, later ones can reference earlier ones
+    pub fn with(mut self, name: &'a str, query: impl Into<CteQuery<'a>>) -> Self {
+        self.ctes.push((name, query.into(), None));
+        self
+    }
+
+    /// same as with, but with materialization hint
+    pub fn with_materialization(mut self, name: &'a str, query: impl Into<CteQuery<'a>>, hint: Materialization) -> Self {
+        self.ctes.push((name, query.into(), Some(hint)));
+        self
+    }
+
+    /// `WITH ..` clause alone, the outer query continues on it ex.: `UpdateBuilder::build_all_after`
+    /// error if a built query with bound values follows other bound values
+    pub fn build_prefix(self) -> Result<QueryBuilder<'a, Postgres>, CteError> {
+        let nl = self.whitespace.newline();
+        let mut query: QueryBuilder<'a, Postgres> = QueryBuilder::new("");
+
+        for (index, (name, cte, hint)) in self.ctes.into_iter().enumerate() {
+            let hint = hint.map(|hint| format!("{0} ", hint.as_sql())).unwrap_or_default();
+
+            if index == 0 {
+                query.push(format!("WITH {0} AS {1}({2}", name, hint, nl));
+            } else {
+                query.push(format!(",{2}{0} AS {1}({2}", name, hint, nl));
+            }
+
+            query = match cte {
+                CteQuery::Expression(expression) => push_expression(expression, query),
+                CteQuery::QueryBuilder(cte) => append_query(query, cte, name)?,
+            };
+            query.push(format!("{0})", nl));
+        }
+
+        if !query.sql().is_empty() {
+            query.push(nl);
+        }
+
+        Ok(query)
+    }
+
+    /// outer: query using the CTEs ex.: `SELECT *\nFROM recent`, only outer is rendered if there are no CTEs
+    /// values of CTEs are bound first, a built outer can bind values only if no CTE does,
+    /// otherwise build outer on `build_prefix` or add outer conditions by `ConditionBuilder` on the result
+    pub fn build(self, outer: BaseQuery<'a>) -> Result<QueryBuilder<'a, Postgres>, CteError> {
+        let mut query = self.build_prefix()?;
+
+        match outer {
+            BaseQuery::Sql(sql) => {
+                query.push(sql);
+            },
+            BaseQuery::QueryBuilder(outer) => query = append_query(query, outer, "outer query")?,
+        }
+
+        Ok(query)
+    }
+}
+
+/// query followed by other, bound values of other are kept if query has none, see `bind_count`
+fn append_query<'a>(query: QueryBuilder<'a, Postgres>, other: QueryBuilder<'a, Postgres>, name: &str) -> Result<QueryBuilder<'a, Postgres>, CteError> {
+    let (mut query, query_binds) = bind_count(query);
+    let (other, other_binds) = bind_count(other);
+
+    if other_binds == 0 {
+        query.push(other.sql());
+        return Ok(query);
+    }
+
+    if query_binds > 0 {
+        return Err(CteError::UnmergeableValues(name.to_string()));
+    }
+
+    Ok(wrap_query(query.sql(), other, ""))
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Postgres, QueryBuilder};
+
+    use crate::{BaseQuery, Column, Condition, ConditionBuilder, CteError, Expression, Materialization, SelectBuilder, UpdateBuilder, WhitespaceStyle, WithBuilder};
+
+    #[test]
+    fn cte_binds_before_outer() {
+        let recent = Expression::new()
+            .sql("SELECT customer_id, total\nFROM orders\nWHERE created_at > ")
+            .value("2024-01-01");
+        let big = Expression::new()
+            .sql("SELECT customer_id\nFROM recent\nWHERE total > ")
+            .value(1000);
+
+        let query = WithBuilder::new()
+            .with("recent", recent)
+            .with("big", big)
+            .build(BaseQuery::Sql("SELECT *\nFROM customers"))
+            .unwrap();
+
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "region", "=", "EU".into(), None),
+            Condition::from_expression(Some("AND"), "id", "IN", Expression::new().sql("id IN (SELECT customer_id FROM big)")),
+        ];
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(query), &conditions, None, None, None, None);
+
+        let result = "WITH recent AS (\nSELECT customer_id, total\nFROM orders\nWHERE created_at > $1\n),\nbig AS (\nSELECT customer_id\nFROM recent\nWHERE total > $2\n)\nSELECT *\nFROM customers\nWHERE\n    region = $3\n    AND id IN (SELECT customer_id FROM big)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn cte_from_condition_builder() {
+        let recent_conditions: Vec<Condition> = vec![Condition::gt("created_at", "2024-01-01")];
+        let recent = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(SelectBuilder::new("orders", vec!["customer_id", "total"]).build()),
+            &recent_conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .build();
+        let big = Expression::new()
+            .sql("SELECT customer_id\nFROM recent\nWHERE total > ")
+            .value(1000);
+
+        let query = WithBuilder::new()
+            .with("recent", recent)
+            .with("big", big)
+            .build(BaseQuery::Sql("SELECT *\nFROM big"))
+            .unwrap();
+
+        let conditions: Vec<Condition> = vec![Condition::eq("region", "EU")];
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(query), &conditions, None, None, None, None);
+
+        let result = "WITH recent AS (\nSELECT customer_id, total\nFROM orders\nWHERE\n    created_at > $1\n),\nbig AS (\nSELECT customer_id\nFROM recent\nWHERE total > $2\n)\nSELECT *\nFROM big\nWHERE\n    region = $3";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn cte_bound_query_after_bound_cte() {
+        let first = Expression::new().sql("SELECT ").value(1);
+        let second_conditions: Vec<Condition> = vec![Condition::eq("id", 2)];
+        let second = ConditionBuilder::new(BaseQuery::Sql("SELECT id\nFROM users"), &second_conditions, None, None, None, None).build();
+
+        let query = WithBuilder::new().with("first", first).with("second", second).build(BaseQuery::Sql("SELECT 1"));
+
+        assert_eq!(query.err(), Some(CteError::UnmergeableValues("second".to_string())));
+    }
+
+    #[test]
+    fn cte_placeholder_in_literal() {
+        let first = Expression::new().sql("SELECT id\nFROM users\nWHERE org_id = ").value(5);
+        let second = QueryBuilder::<Postgres>::new("SELECT id\nFROM notes\nWHERE body <> '$1' /* $2 */");
+        let outer_conditions: Vec<Condition> = vec![Condition::eq("id", 2)];
+        let outer = ConditionBuilder::new(BaseQuery::Sql("SELECT '$9' AS label"), &outer_conditions, None, None, None, None).build();
+
+        let query = WithBuilder::new()
+            .with("first", first)
+            .with("second", second)
+            .build(BaseQuery::Sql("SELECT *\nFROM second"))
+            .unwrap();
+
+        let result = "WITH first AS (\nSELECT id\nFROM users\nWHERE org_id = $1\n),\nsecond AS (\nSELECT id\nFROM notes\nWHERE body <> '$1' /* $2 */\n)\nSELECT *\nFROM second";
+
+        assert_eq!(query.into_sql(), result);
+
+        let bound_outer = WithBuilder::new()
+            .with("second", QueryBuilder::<Postgres>::new("SELECT '$1'"))
+            .build(BaseQuery::QueryBuilder(outer))
+            .unwrap();
+
+        assert_eq!(bound_outer.into_sql(), "WITH second AS (\nSELECT '$1'\n)\nSELECT '$9' AS label\nWHERE\n    id = $1");
+    }
+
+    #[test]
+    fn cte_materialization_hints() {
+        let totals = Expression::new().sql("SELECT customer_id, sum(total) AS total\nFROM orders\nGROUP BY customer_id");
+        let active = Expression::new().sql("SELECT id\nFROM customers\nWHERE active");
+
+        let query = WithBuilder::new()
+            .with_materialization("totals", totals, Materialization::Materialized)
+            .with_materialization("active", active, Materialization::NotMaterialized)
+            .build(BaseQuery::Sql("SELECT *\nFROM totals\nJOIN active ON active.id = totals.customer_id"))
+            .unwrap();
+
+        let result = "WITH totals AS MATERIALIZED (\nSELECT customer_id, sum(total) AS total\nFROM orders\nGROUP BY customer_id\n),\nactive AS NOT MATERIALIZED (\nSELECT id\nFROM customers\nWHERE active\n)\nSELECT *\nFROM totals\nJOIN active ON active.id = totals.customer_id";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn without_ctes() {
+        assert_eq!(WithBuilder::new().build(BaseQuery::Sql("SELECT 1")).unwrap().into_sql(), "SELECT 1");
+    }
+
+    #[test]
+    fn cte_with_bound_outer_update() {
+        let stale_conditions: Vec<Condition> = vec![Condition::lt("last_seen", "2024-01-01")];
+        let stale = ConditionBuilder::new(BaseQuery::Sql("SELECT id\nFROM sessions"), &stale_conditions, None, None, None, None).build();
+        let columns: Vec<Column> = vec![("active", false.into())];
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("org_id", 5),
+            Condition::from_expression(Some("AND"), "id", "IN", Expression::new().sql("id IN (SELECT id FROM stale)")),
+        ];
+        let mut update = UpdateBuilder::new("sessions", columns, conditions, None);
+
+        let prefix = WithBuilder::new().with("stale", stale).build_prefix().unwrap();
+        let query = update.build_all_after(prefix);
+
+        let result = "WITH stale AS (\nSELECT id\nFROM sessions\nWHERE\n    last_seen < $1\n)\nUPDATE sessions\n    SET active = $2\nWHERE\n    org_id = $3\n    AND id IN (SELECT id FROM stale)";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn cte_single_line() {
+        let conditions: Vec<Condition> = vec![Condition::eq("org_id", 5)];
+        let recent = SelectBuilder::new("orders", vec!["id"])
+            .whitespace(WhitespaceStyle::SingleLine)
+            .with_conditions(&conditions)
+            .unwrap()
+            .build();
+
+        let test_query = WithBuilder::new()
+            .whitespace(WhitespaceStyle::SingleLine)
+            .with("recent", recent)
+            .build(BaseQuery::Sql("SELECT * FROM recent"))
+            .unwrap();
+
+        assert_eq!(test_query.into_sql(), "WITH recent AS ( SELECT id FROM orders WHERE org_id = $1 ) SELECT * FROM recent");
+    }
+}
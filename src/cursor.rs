@@ -0,0 +1,198 @@
+use std::fmt;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use hmac::{Hmac, Mac};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use sha2::Sha256;
+
+use crate::{Condition, Expression, SqlValue};
+
+/// keyset pagination position, sort key values of the last row of a page
+/// encoded as opaque url safe token for api clients
+/// `encode`/`decode` tokens can be edited by clients, use `encode_signed`/`decode_signed` to detect tampering
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CursorToken {
+    pub columns: Vec<String>,
+    pub values: Vec<SqlValue>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CursorError {
+    /// token is not valid base64 or does not contain a cursor of scalar values
+    Malformed,
+    /// signature of token does not match its content
+    Tampered,
+    /// token was created for other sort columns ex.: sort order of the endpoint changed
+    Stale { expected: Vec<String>, found: Vec<String> },
+}
+
+impl fmt::Display for CursorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CursorError::Malformed => write!(f, "cursor token is malformed"),
+            CursorError::Tampered => write!(f, "cursor token signature does not match"),
+            CursorError::Stale { expected, found } => write!(
+                f,
+                "cursor token is for columns ({0}), expected ({1})",
+                found.join(", "),
+                expected.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CursorError {}
+
+impl CursorToken {
+    pub fn new(columns: Vec<&str>, values: Vec<SqlValue>) -> Self {
+        Self {
+            columns: columns.into_iter().map(String::from).collect(),
+            values,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    /// token signed with HMAC-SHA256 of key ex.: `<cursor>.<signature>`, see `decode_signed`
+    pub fn encode_signed(&self, key: &[u8]) -> String {
+        let payload = self.encode();
+        let signature = URL_SAFE_NO_PAD.encode(Self::signature(&payload, key).finalize().into_bytes());
+
+        format!("{0}.{1}", payload, signature)
+    }
+
+    /// same as decode, but signature of token must match key
+    pub fn decode_signed(token: &str, columns: &[&str], key: &[u8]) -> Result<Self, CursorError> {
+        let (payload, signature) = token.split_once('.').ok_or(CursorError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD.decode(signature).map_err(|_| CursorError::Malformed)?;
+
+        Self::signature(payload, key)
+            .verify_slice(&signature)
+            .map_err(|_| CursorError::Tampered)?;
+
+        Self::decode(payload, columns)
+    }
+
+    /// columns: sort columns of the query, token must have been created for the same columns
+    /// only scalar values are accepted as token content comes from clients
+    pub fn decode(token: &str, columns: &[&str]) -> Result<Self, CursorError> {
+        let json = URL_SAFE_NO_PAD.decode(token).map_err(|_| CursorError::Malformed)?;
+        let cursor: Self = serde_json::from_slice(&json).map_err(|_| CursorError::Malformed)?;
+
+        if cursor.columns.len() != cursor.values.len() || !cursor.values.iter().all(Self::is_scalar) {
+            return Err(CursorError::Malformed);
+        }
+
+        if cursor.columns.iter().map(String::as_str).ne(columns.iter().copied()) {
+            return Err(CursorError::Stale {
+                expected: columns.iter().map(|column| column.to_string()).collect(),
+                found: cursor.columns,
+            });
+        }
+
+        Ok(cursor)
+    }
+
+    fn is_scalar(value: &SqlValue) -> bool {
+        match value {
+            SqlValue::GenericValue(Value::Array(_) | Value::Object(_)) => false,
+            SqlValue::GenericValue(_) | SqlValue::NaiveChrono(_) => true,
+            #[cfg(feature = "uuid")]
+            SqlValue::Uuid(_) => true,
+            #[cfg(feature = "decimal")]
+            SqlValue::Decimal(_) => true,
+            _ => false,
+        }
+    }
+
+    fn signature(payload: &str, key: &[u8]) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts keys of any length");
+        mac.update(payload.as_bytes());
+
+        mac
+    }
+
+    /// rows after the cursor ex.: `(created_at, id) > ($1, $2)`, use `<` for descending order
+    pub fn condition<'a>(&'a self, chain_opr: Option<&'a str>, eq_opr: &'a str) -> Condition<'a> {
+        let mut expression = Expression::new().sql(format!("({0}) {1} (", self.columns.join(", "), eq_opr));
+
+        for (index, value) in self.values.iter().enumerate() {
+            if index > 0 {
+                expression = expression.sql(", ");
+            }
+
+            expression = expression.value(value.clone());
+        }
+
+        let column = self.columns.first().map(String::as_str).unwrap_or_default();
+
+        Condition::from_expression(chain_opr, column, eq_opr, expression.sql(")"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{BaseQuery, ConditionBuilder, CursorError, CursorToken, SqlValue};
+
+    #[test]
+    fn cursor_round_trip() {
+        let created_at = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().and_hms_opt(10, 30, 0).unwrap();
+        let token = CursorToken::new(vec!["created_at", "id"], vec![created_at.into(), 42.into()]).encode();
+
+        let cursor = CursorToken::decode(&token, &["created_at", "id"]).unwrap();
+        let conditions = vec![cursor.condition(None, ">")];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql("SELECT *\nFROM events"), &conditions, None, Some(20), None, None);
+
+        assert_eq!(serde_json::to_string(&cursor.values).unwrap(), r#"[{"NaiveChrono":{"NaiveDateTime":"2024-01-15T10:30:00"}},{"GenericValue":42}]"#);
+        assert_eq!(
+            test_query.build().into_sql(),
+            "SELECT *\nFROM events\nWHERE\n    (created_at, id) > ($1, $2)\nLIMIT $3"
+        );
+    }
+
+    #[test]
+    fn cursor_malformed_and_stale() {
+        let token = CursorToken::new(vec!["created_at", "id"], vec!["2024-01-15".into(), 42.into()]).encode();
+
+        assert_eq!(CursorToken::decode("not a token!", &["id"]).err(), Some(CursorError::Malformed));
+        assert_eq!(CursorToken::decode("bm90IGpzb24", &["id"]).err(), Some(CursorError::Malformed));
+        assert_eq!(
+            CursorToken::decode(&token, &["id"]).err(),
+            Some(CursorError::Stale { expected: vec!["id".to_string()], found: vec!["created_at".to_string(), "id".to_string()] })
+        );
+    }
+
+    #[test]
+    fn cursor_forged_record() {
+        let forged = CursorToken::new(
+            vec!["id"],
+            vec![SqlValue::Record { type_name: "int; DROP TABLE users; --".to_string(), fields: vec![1.into()] }],
+        )
+        .encode();
+        let array = CursorToken::new(vec!["id"], vec![vec![1, 2].into()]).encode();
+
+        assert_eq!(CursorToken::decode(&forged, &["id"]).err(), Some(CursorError::Malformed));
+        assert_eq!(CursorToken::decode(&array, &["id"]).err(), Some(CursorError::Malformed));
+    }
+
+    #[test]
+    fn cursor_signed() {
+        let cursor = CursorToken::new(vec!["id"], vec![42.into()]);
+        let token = cursor.encode_signed(b"secret");
+        let edited = format!("{0}.{1}", CursorToken::new(vec!["id"], vec![1.into()]).encode(), token.split_once('.').unwrap().1);
+
+        assert!(CursorToken::decode_signed(&token, &["id"], b"secret").is_ok());
+        assert_eq!(CursorToken::decode_signed(&token, &["id"], b"other").err(), Some(CursorError::Tampered));
+        assert_eq!(CursorToken::decode_signed(&edited, &["id"], b"secret").err(), Some(CursorError::Tampered));
+        assert_eq!(CursorToken::decode_signed(&cursor.encode(), &["id"], b"secret").err(), Some(CursorError::Malformed));
+    }
+}
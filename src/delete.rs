@@ -0,0 +1,235 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{BaseQuery, Condition, ConditionBuilder, OrderBy, WhitespaceStyle, WindowSpec, is_valid_identifier, tag_comment, wrap_query};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DeleteError {
+    /// id, partition or order column of `build_dedup` is not a plain identifier
+    InvalidIdentifier(String),
+}
+
+impl fmt::Display for DeleteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DeleteError::InvalidIdentifier(name) => write!(f, "`{name}` is not a valid identifier"),
+        }
+    }
+}
+
+impl std::error::Error for DeleteError {}
+
+#[derive(Debug)]
+pub struct DeleteBuilder<'a> {
+    pub table: &'a str,
+    pub conditions: Vec<Condition<'a>>,
+    pub end: Option<&'a str>,
+    /// without rendered conditions nothing is built unless delete of all rows is allowed, see `allow_delete_all`
+    pub delete_all: bool,
+    /// rendered as trailing comment by `build_all`, see `tag_comment`
+    pub tag: Option<BTreeMap<&'a str, &'a str>>,
+    /// line layout of built query, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
+}
+
+impl<'a> DeleteBuilder<'a> {
+    /// table: table name
+    /// conditions: for restricting deleted rows
+    /// end: additional query part goes to end of delete query ex.: `RETURNING id`
+    pub fn new(
+        table: &'a str,
+        conditions: Vec<Condition<'a>>,
+        end: Option<&'a str>,
+    ) -> Self {
+        Self {
+            table,
+            conditions,
+            end,
+            delete_all: false,
+            tag: None,
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    /// deleting all rows when there are no conditions is allowed
+    pub fn allow_delete_all(mut self) -> Self {
+        self.delete_all = true;
+        self
+    }
+
+    pub fn tag(mut self, tag: BTreeMap<&'a str, &'a str>) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    /// empty query if no condition renders (ex.: none given, BETWEEN without value_r) and delete of all rows is not allowed
+    pub fn build_all(&mut self) -> QueryBuilder<'_, Postgres> {
+        if !self.conditions.iter().any(Condition::renders) && !self.delete_all {
+            return QueryBuilder::new("");
+        }
+
+        let query: QueryBuilder<'_, Postgres> = QueryBuilder::new(format!("DELETE FROM {}", self.table));
+
+        let mut condition_builder = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(query),
+            &self.conditions,
+            None,
+            None,
+            None,
+            self.end,
+        );
+        condition_builder.tag = self.tag.clone();
+        condition_builder.whitespace = self.whitespace;
+
+        condition_builder.build()
+    }
+
+    /// deletes all but first row per key, conditions restrict which rows are considered
+    /// ex.: `DELETE FROM t WHERE id IN (SELECT id FROM (SELECT id, row_number() OVER (PARTITION BY key ORDER BY ts DESC) AS rn FROM t) ranked WHERE rn > 1)`
+    /// without partition_by all but first row of the table are deleted, error if a column is not a plain identifier
+    pub fn build_dedup(&mut self, id_column: &str, partition_by: &[&str], order_by: OrderBy) -> Result<QueryBuilder<'_, Postgres>, DeleteError> {
+        let columns = std::iter::once(&id_column)
+            .chain(partition_by)
+            .chain(order_by.iter().map(|(column, _)| column));
+
+        for column in columns {
+            if !is_valid_identifier(column) {
+                return Err(DeleteError::InvalidIdentifier(column.to_string()));
+            }
+        }
+
+        let window = WindowSpec::new(partition_by.to_vec(), order_by);
+        let nl = self.whitespace.newline();
+        let indent = self.whitespace.indent();
+
+        let ranked: QueryBuilder<'_, Postgres> = QueryBuilder::new(format!(
+            "SELECT {0}, row_number() OVER ({1}) AS rn{2}FROM {3}",
+            id_column,
+            window.as_sql(),
+            nl,
+            self.table
+        ));
+
+        let ranked = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(ranked),
+            &self.conditions,
+            None,
+            None,
+            None,
+            None,
+        ).whitespace(self.whitespace).build();
+
+        let prefix = format!("DELETE FROM {0}{1}WHERE{2}{3} IN ({1}SELECT {3}{1}FROM ({1}", self.table, nl, indent, id_column);
+        let mut suffix = format!("{0}) ranked{0}WHERE{1}rn > 1{0})", nl, indent);
+
+        if let Some(end) = self.end {
+            suffix.push_str(&format!("{0}{1}", nl, end));
+        }
+
+        if let Some(tag) = &self.tag {
+            suffix.push_str(&format!("{0}{1}", nl, tag_comment(tag)));
+        }
+
+        Ok(wrap_query(&prefix, ranked, &suffix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Condition, DeleteBuilder, DeleteError, SortDirection, WhitespaceStyle};
+
+    #[test]
+    fn delete_with_empty_conditions() {
+        let conditions: Vec<Condition> = Vec::new();
+
+        let mut test_query = DeleteBuilder::new("sample_table", conditions.clone(), None);
+        let mut allowed_query = DeleteBuilder::new("sample_table", conditions, None).allow_delete_all();
+
+        assert_eq!(test_query.build_all().into_sql(), "");
+        assert_eq!(allowed_query.build_all().into_sql(), "DELETE FROM sample_table");
+    }
+
+    #[test]
+    fn delete_with_condition() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+
+        let mut test_query = DeleteBuilder::new("sample_table", conditions, None);
+        let result = "DELETE FROM sample_table\nWHERE\n    id = $1";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn delete_with_returning() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "status", "=", "expired".into(), None),
+            Condition::new(Some("AND"), "created_at", "<", "2023-01-01".into(), None),
+        ];
+
+        let mut test_query = DeleteBuilder::new("sessions", conditions, Some("RETURNING id"));
+        let result = "DELETE FROM sessions\nWHERE\n    status = $1\n    AND created_at < $2\nRETURNING id";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn delete_duplicates_keep_newest() {
+        let conditions = vec![Condition::new(None, "tenant_id", "=", 5.into(), None)];
+
+        let mut test_query = DeleteBuilder::new("events", conditions, Some("RETURNING id"));
+        let result = "DELETE FROM events\nWHERE\n    id IN (\nSELECT id\nFROM (\nSELECT id, row_number() OVER (PARTITION BY device_id, kind ORDER BY created_at DESC) AS rn\nFROM events\nWHERE\n    tenant_id = $1\n) ranked\nWHERE\n    rn > 1\n)\nRETURNING id";
+
+        assert_eq!(test_query.build_dedup("id", &["device_id", "kind"], vec![("created_at", SortDirection::Desc)]).unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn delete_duplicates_without_partition() {
+        let conditions: Vec<Condition> = Vec::new();
+
+        let mut test_query = DeleteBuilder::new("events", conditions, None);
+        let result = "DELETE FROM events\nWHERE\n    id IN (\nSELECT id\nFROM (\nSELECT id, row_number() OVER (ORDER BY created_at DESC) AS rn\nFROM events\n) ranked\nWHERE\n    rn > 1\n)";
+
+        assert_eq!(test_query.build_dedup("id", &[], vec![("created_at", SortDirection::Desc)]).unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn delete_duplicates_invalid_column() {
+        let conditions: Vec<Condition> = Vec::new();
+        let mut test_query = DeleteBuilder::new("events", conditions, None);
+
+        assert_eq!(
+            test_query.build_dedup("id", &["kind; DROP TABLE events"], Vec::new()).err(),
+            Some(DeleteError::InvalidIdentifier("kind; DROP TABLE events".to_string()))
+        );
+        assert!(test_query.build_dedup("id", &["kind"], vec![("created_at DESC", SortDirection::Desc)]).is_err());
+        assert!(test_query.build_dedup("id)", &["kind"], Vec::new()).is_err());
+    }
+
+    #[test]
+    fn delete_with_only_invalid_conditions() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "id", "IN", 5.into(), None),
+            Condition::new(Some("AND"), "created_at", "BETWEEN", "2024-01-01".into(), None),
+        ];
+
+        let mut test_query = DeleteBuilder::new("sample_table", conditions, None);
+
+        assert_eq!(test_query.build_all().into_sql(), "");
+    }
+
+    #[test]
+    fn delete_single_line() {
+        let conditions: Vec<Condition> = vec![Condition::eq("id", 5)];
+
+        let mut test_query = DeleteBuilder::new("sample_table", conditions, Some("RETURNING id")).whitespace(WhitespaceStyle::SingleLine);
+
+        assert_eq!(test_query.build_all().into_sql(), "DELETE FROM sample_table WHERE id = $1 RETURNING id");
+    }
+}
@@ -0,0 +1,225 @@
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{SqlValue, bound_values, push_sqlvalue, sql_literal};
+
+/// part of an expression, either raw sql or a value that will be bound
+#[derive(Debug, Clone)]
+pub enum ExpressionPart {
+    Sql(String),
+    Value(SqlValue),
+    /// pushes the placeholder of an already bound value again instead of binding it twice,
+    /// index is the position of the value among the `Value` parts of the expression
+    BoundValue(usize),
+}
+
+/// free sql expression with bound values ex.: `lower(email) = lower($1)`
+#[derive(Debug, Clone, Default)]
+pub struct Expression {
+    pub parts: Vec<ExpressionPart>,
+}
+
+impl Expression {
+    pub fn new() -> Self {
+        Self { parts: Vec::new() }
+    }
+
+    pub fn sql(mut self, sql: impl Into<String>) -> Self {
+        self.parts.push(ExpressionPart::Sql(sql.into()));
+        self
+    }
+
+    pub fn value(mut self, value: impl Into<SqlValue>) -> Self {
+        self.parts.push(ExpressionPart::Value(value.into()));
+        self
+    }
+
+    pub fn bound_value(mut self, index: usize) -> Self {
+        self.parts.push(ExpressionPart::BoundValue(index));
+        self
+    }
+
+    /// appends parts of other expression, indexes of `BoundValue` parts of other expression are shifted
+    pub fn expression(mut self, other: Expression) -> Self {
+        let offset = self.value_count();
+
+        for part in other.parts {
+            match part {
+                ExpressionPart::BoundValue(index) => {
+                    self.parts.push(ExpressionPart::BoundValue(index + offset));
+                },
+                part => self.parts.push(part),
+            }
+        }
+
+        self
+    }
+
+    /// DEBUGGING AID ONLY, the result must never be executed
+    /// sql with quoted literals of values in place of their placeholders ex.: `lower(email) = lower('a@b.c')`
+    pub fn preview_sql(&self) -> String {
+        let values: Vec<&SqlValue> = self
+            .parts
+            .iter()
+            .filter_map(|part| match part {
+                ExpressionPart::Value(value) => Some(value),
+                _ => None,
+            })
+            .collect();
+
+        self.parts
+            .iter()
+            .map(|part| match part {
+                ExpressionPart::Sql(sql) => sql.clone(),
+                ExpressionPart::Value(value) => sql_literal(value),
+                ExpressionPart::BoundValue(index) => values.get(*index).map(|value| sql_literal(value)).unwrap_or_default(),
+            })
+            .collect()
+    }
+
+    /// values bound when the expression is rendered, in order
+    pub fn bound_values(&self) -> Vec<SqlValue> {
+        self.parts
+            .iter()
+            .flat_map(|part| match part {
+                ExpressionPart::Value(value) => bound_values(value),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    fn value_count(&self) -> usize {
+        self.parts
+            .iter()
+            .filter(|part| matches!(part, ExpressionPart::Value(_)))
+            .count()
+    }
+}
+
+impl From<&str> for Expression {
+    fn from(value: &str) -> Self {
+        Self::new().sql(value)
+    }
+}
+
+impl From<String> for Expression {
+    fn from(value: String) -> Self {
+        Self::new().sql(value)
+    }
+}
+
+/// `CASE WHEN .. THEN .. ELSE .. END` expression, branch values are bound
+/// whens: list of (when_condition, then_value)
+/// else_value: value for ELSE branch, ELSE omitted if None
+#[derive(Debug, Clone)]
+pub struct CaseExpr {
+    pub whens: Vec<(Expression, SqlValue)>,
+    pub else_value: Option<SqlValue>,
+}
+
+impl CaseExpr {
+    pub fn new(whens: Vec<(Expression, SqlValue)>, else_value: Option<SqlValue>) -> Self {
+        Self { whens, else_value }
+    }
+}
+
+impl From<CaseExpr> for Expression {
+    fn from(value: CaseExpr) -> Self {
+        let mut expression = Expression::new().sql("(CASE");
+
+        for (when_condition, then_value) in value.whens {
+            expression = expression
+                .sql(" WHEN ")
+                .expression(when_condition)
+                .sql(" THEN ")
+                .value(then_value);
+        }
+
+        if let Some(else_value) = value.else_value {
+            expression = expression.sql(" ELSE ").value(else_value);
+        }
+
+        expression.sql(" END)")
+    }
+}
+
+pub fn push_expression(expression: Expression, mut query_builder: QueryBuilder<'_, Postgres>) -> QueryBuilder<'_, Postgres> {
+    let mut placeholders: Vec<String> = Vec::new();
+
+    for part in expression.parts {
+        match part {
+            ExpressionPart::Sql(sql) => { query_builder.push(sql); },
+            ExpressionPart::Value(value) => {
+                let start = query_builder.sql().len();
+                query_builder = push_sqlvalue(value, query_builder);
+                placeholders.push(query_builder.sql()[start..].to_string());
+            },
+            ExpressionPart::BoundValue(index) => {
+                if let Some(placeholder) = placeholders.get(index) {
+                    query_builder.push(placeholder);
+                }
+            },
+        }
+    }
+
+    query_builder
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Postgres, QueryBuilder};
+
+    use crate::{CaseExpr, Expression, push_expression};
+
+    #[test]
+    fn expression_with_values() {
+        let expression = Expression::new()
+            .sql("lower(email) = lower(")
+            .value("Sample@Mail.com")
+            .sql(")");
+
+        let query = push_expression(expression, QueryBuilder::<Postgres>::new(""));
+
+        assert_eq!(query.into_sql(), "lower(email) = lower($1)");
+    }
+
+    #[test]
+    fn expression_with_bound_value() {
+        let expression = Expression::new()
+            .sql("(flags & ")
+            .value(4)
+            .sql(") = ")
+            .bound_value(0);
+
+        let query = push_expression(expression, QueryBuilder::<Postgres>::new(""));
+
+        assert_eq!(query.into_sql(), "(flags & $1) = $1");
+    }
+
+    #[test]
+    fn expression_preview() {
+        let expression = Expression::new()
+            .sql("(flags & ")
+            .value(4)
+            .sql(") = ")
+            .bound_value(0)
+            .sql(" AND note <> '$1' AND name = ")
+            .value("O'Brien");
+
+        assert_eq!(expression.preview_sql(), "(flags & 4) = 4 AND note <> '$1' AND name = 'O''Brien'");
+    }
+
+    #[test]
+    fn case_expression() {
+        let case = CaseExpr::new(
+            vec![
+                ("premium".into(), 2.into()),
+                (Expression::new().sql("age > ").value(60), 3.into()),
+            ],
+            Some(1.into()),
+        );
+
+        let query = push_expression(case.into(), QueryBuilder::<Postgres>::new(""));
+
+        assert_eq!(query.into_sql(), "(CASE WHEN premium THEN $1 WHEN age > $2 THEN $3 ELSE $4 END)");
+    }
+}
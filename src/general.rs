@@ -1,8 +1,13 @@
-use sqlx::{Postgres, QueryBuilder};
+use sqlx::{Arguments, Execute, Postgres, QueryBuilder};
 use serde::{Serialize, Deserialize};
 use serde_json::{Value, Number};
-use chrono::{NaiveDate, NaiveDateTime};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, Utc};
 use std::convert::From;
+use std::fmt;
+#[cfg(feature = "uuid")]
+use uuid::Uuid;
+#[cfg(feature = "decimal")]
+use rust_decimal::Decimal;
 
 pub enum BaseQuery<'a> {
     Sql(&'a str),
@@ -12,13 +17,27 @@ pub enum BaseQuery<'a> {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum NaiveChrono {
     NaiveDate(NaiveDate),
-    NaiveDateTime(NaiveDateTime)
+    NaiveDateTime(NaiveDateTime),
+    /// bound as timestamptz
+    DateTimeUtc(DateTime<Utc>),
+    /// bound as timestamptz, offset is kept
+    DateTimeFixed(DateTime<FixedOffset>),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SqlValue {
     GenericValue(Value),
-    NaiveChrono(NaiveChrono)
+    NaiveChrono(NaiveChrono),
+    /// composite value, fields bound in order ex.: `ROW($1, $2)::address`
+    Record { type_name: String, fields: Vec<SqlValue> },
+    #[cfg(feature = "uuid")]
+    Uuid(Uuid),
+    /// bound as `uuid[]`, expanded to a list for IN
+    #[cfg(feature = "uuid")]
+    UuidArray(Vec<Uuid>),
+    /// bound as numeric without going through f64 ex.: money columns
+    #[cfg(feature = "decimal")]
+    Decimal(Decimal),
 }
 
 impl From<&Value> for SqlValue {
@@ -111,9 +130,73 @@ impl From<isize> for SqlValue {
     }
 }
 
+/// values above i64::MAX are bound as numeric, see `BoundNumber`
 impl From<u64> for SqlValue {
     fn from(value: u64) -> Self {
-        Self::GenericValue(Value::Number(Number::from(value as i64)))
+        Self::GenericValue(Value::Number(Number::from(value)))
+    }
+}
+
+impl From<u8> for SqlValue {
+    fn from(value: u8) -> Self {
+        Self::GenericValue(Value::Number(Number::from(value)))
+    }
+}
+
+impl From<u16> for SqlValue {
+    fn from(value: u16) -> Self {
+        Self::GenericValue(Value::Number(Number::from(value)))
+    }
+}
+
+impl From<u32> for SqlValue {
+    fn from(value: u32) -> Self {
+        Self::GenericValue(Value::Number(Number::from(value)))
+    }
+}
+
+/// values above i64::MAX are clamped to i64::MAX
+impl From<usize> for SqlValue {
+    fn from(value: usize) -> Self {
+        Self::GenericValue(Value::Number(Number::from(i64::try_from(value).unwrap_or(i64::MAX))))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlValueError {
+    /// NaN and infinity have no json number, bind them with an explicit cast instead
+    NonFiniteFloat(f64),
+}
+
+impl fmt::Display for SqlValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SqlValueError::NonFiniteFloat(value) => write!(f, "float {value} is not finite"),
+        }
+    }
+}
+
+impl std::error::Error for SqlValueError {}
+
+fn finite_float(value: f64) -> Result<Value, SqlValueError> {
+    Number::from_f64(value).map(Value::Number).ok_or(SqlValueError::NonFiniteFloat(value))
+}
+
+/// error for NaN and infinity
+impl TryFrom<f32> for SqlValue {
+    type Error = SqlValueError;
+
+    fn try_from(value: f32) -> Result<Self, Self::Error> {
+        finite_float(value.into()).map(Self::GenericValue)
+    }
+}
+
+/// error for NaN and infinity
+impl TryFrom<f64> for SqlValue {
+    type Error = SqlValueError;
+
+    fn try_from(value: f64) -> Result<Self, Self::Error> {
+        finite_float(value).map(Self::GenericValue)
     }
 }
 
@@ -147,6 +230,24 @@ impl From<Vec<isize>> for SqlValue {
     }
 }
 
+/// error for first NaN or infinity element
+impl TryFrom<Vec<f32>> for SqlValue {
+    type Error = SqlValueError;
+
+    fn try_from(value: Vec<f32>) -> Result<Self, Self::Error> {
+        value.into_iter().map(|item| finite_float(item.into())).collect::<Result<Vec<Value>, _>>().map(|items| Self::GenericValue(items.into()))
+    }
+}
+
+/// error for first NaN or infinity element
+impl TryFrom<Vec<f64>> for SqlValue {
+    type Error = SqlValueError;
+
+    fn try_from(value: Vec<f64>) -> Result<Self, Self::Error> {
+        value.into_iter().map(finite_float).collect::<Result<Vec<Value>, _>>().map(|items| Self::GenericValue(items.into()))
+    }
+}
+
 impl From<NaiveDate> for SqlValue {
     fn from(value: NaiveDate) -> Self {
         Self::NaiveChrono(NaiveChrono::NaiveDate(value))
@@ -159,20 +260,90 @@ impl From<NaiveDateTime> for SqlValue {
     }
 }
 
+#[cfg(feature = "uuid")]
+impl From<Uuid> for SqlValue {
+    fn from(value: Uuid) -> Self {
+        Self::Uuid(value)
+    }
+}
+
+#[cfg(feature = "uuid")]
+impl From<Vec<Uuid>> for SqlValue {
+    fn from(value: Vec<Uuid>) -> Self {
+        Self::UuidArray(value)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<Decimal> for SqlValue {
+    fn from(value: Decimal) -> Self {
+        Self::Decimal(value)
+    }
+}
+
+impl From<DateTime<Utc>> for SqlValue {
+    fn from(value: DateTime<Utc>) -> Self {
+        Self::NaiveChrono(NaiveChrono::DateTimeUtc(value))
+    }
+}
+
+impl From<DateTime<FixedOffset>> for SqlValue {
+    fn from(value: DateTime<FixedOffset>) -> Self {
+        Self::NaiveChrono(NaiveChrono::DateTimeFixed(value))
+    }
+}
+
+/// None is converted to sql NULL
+impl<T: Into<SqlValue>> From<Option<T>> for SqlValue {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(v) => v.into(),
+            None => Self::GenericValue(Value::Null),
+        }
+    }
+}
+
+/// None is converted to sql NULL
+impl<T: Clone + Into<SqlValue>> From<&Option<T>> for SqlValue {
+    fn from(value: &Option<T>) -> Self {
+        value.clone().into()
+    }
+}
+
+/// json number as it is bound, bigint if it fits, u64 above i64::MAX as text cast to numeric
+/// ex.: `$1::numeric`, otherwise double precision
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoundNumber {
+    Integer(i64),
+    Unsigned(u64),
+    Float(f64),
+}
+
+impl BoundNumber {
+    pub fn new(number: &Number) -> Self {
+        match (number.as_i64(), number.as_u64()) {
+            (Some(integer), _) => Self::Integer(integer),
+            (None, Some(unsigned)) => Self::Unsigned(unsigned),
+            _ => Self::Float(number.as_f64().unwrap_or_default()),
+        }
+    }
+
+    pub fn push(self, mut query: QueryBuilder<'_, Postgres>) -> QueryBuilder<'_, Postgres> {
+        match self {
+            Self::Integer(v) => { query.push_bind(v); },
+            Self::Unsigned(v) => { query.push_bind(v.to_string()).push("::numeric"); },
+            Self::Float(v) => { query.push_bind(v); },
+        }
+
+        query
+    }
+}
 
 pub fn push_jsonvalue(value: Value, mut query_builder: QueryBuilder<'_, Postgres>) -> QueryBuilder<'_, Postgres> {
     match value {
-        Value::Null => {},
+        Value::Null => { query_builder.push("NULL"); },
         Value::Bool(v) => { query_builder.push_bind(v); },
-        Value::Number(v) => { 
-            if v.is_f64() {
-                query_builder.push_bind(v.as_f64());
-            }
-
-            if v.is_i64() || v.is_u64() {
-                query_builder.push_bind(v.as_i64());
-            }
-         },
+        Value::Number(v) => { query_builder = BoundNumber::new(&v).push(query_builder); },
         Value::String(v) => { query_builder.push_bind(v); },
         Value::Array(v) => { query_builder.push_bind(v); },
         Value::Object(_) => { query_builder.push_bind(value); },
@@ -188,9 +359,711 @@ pub fn push_sqlvalue(value: SqlValue, mut query_builder: QueryBuilder<'_, Postgr
             match naive_chrono {
                 NaiveChrono::NaiveDate(nd) => { query_builder.push_bind(nd); },
                 NaiveChrono::NaiveDateTime(ndt) => { query_builder.push_bind(ndt); },
+                NaiveChrono::DateTimeUtc(dt) => { query_builder.push_bind(dt); },
+                NaiveChrono::DateTimeFixed(dt) => { query_builder.push_bind(dt); },
             }
-            
-            return query_builder
+
+            query_builder
         },
+        SqlValue::Record { type_name, fields } => {
+            let fields_len = fields.len();
+            query_builder.push("ROW(");
+
+            for (index, field) in fields.into_iter().enumerate() {
+                query_builder = push_sqlvalue(field, query_builder);
+
+                if index < fields_len - 1 {
+                    query_builder.push(", ");
+                }
+            }
+
+            query_builder.push(format!(")::{0}", type_name));
+            query_builder
+        },
+        #[cfg(feature = "uuid")]
+        SqlValue::Uuid(uuid) => {
+            query_builder.push_bind(uuid);
+            query_builder
+        },
+        #[cfg(feature = "uuid")]
+        SqlValue::UuidArray(uuids) => {
+            query_builder.push_bind(uuids);
+            query_builder
+        },
+        #[cfg(feature = "decimal")]
+        SqlValue::Decimal(decimal) => {
+            query_builder.push_bind(decimal);
+            query_builder
+        },
+    }
+}
+
+/// Postgres truncates identifiers longer than this
+const MAX_IDENTIFIER_LEN: usize = 63;
+
+/// name is a plain (optionally schema qualified) identifier which is safe to put in sql without quoting
+pub fn is_valid_identifier(name: &str) -> bool {
+    name.split('.').all(|part| {
+        let mut chars = part.chars();
+
+        match chars.next() {
+            Some(first) if first.is_ascii_alphabetic() || first == '_' => {},
+            _ => return false,
+        }
+
+        part.len() <= MAX_IDENTIFIER_LEN && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// name as quoted identifier, embedded double quotes are doubled, dots are not split
+/// ex.: `order` gives `"order"`, `MyCol` gives `"MyCol"`, see `quote_qualified` for `schema.table`
+pub fn quote_ident(name: &str) -> String {
+    format!("\"{0}\"", name.replace('"', "\"\""))
+}
+
+/// each dot separated part quoted ex.: `public.Order` gives `"public"."Order"`
+pub fn quote_qualified(name: &str) -> String {
+    name.split('.').map(quote_ident).collect::<Vec<String>>().join(".")
+}
+
+/// wraps already built query with sql parts, bound values of inner query are kept
+/// values bound to the result afterwards continue numbering after inner ones
+pub fn wrap_query<'a>(prefix: &str, mut inner: QueryBuilder<'a, Postgres>, suffix: &str) -> QueryBuilder<'a, Postgres> {
+    let mut built = inner.build();
+    let sql = format!("{0}{1}{2}", prefix, built.sql(), suffix);
+    let arguments = built.take_arguments().unwrap_or_default();
+
+    QueryBuilder::with_arguments(sql, arguments)
+}
+
+/// whole result of inner query as one json array ex.: `SELECT coalesce(json_agg(t), '[]') FROM (<inner>) t`
+pub fn as_json_array(inner: QueryBuilder<'_, Postgres>, ws: WhitespaceStyle) -> QueryBuilder<'_, Postgres> {
+    let nl = ws.newline();
+
+    wrap_query(&format!("SELECT coalesce(json_agg(t), '[]'){0}FROM ({0}", nl), inner, &format!("{0}) t", nl))
+}
+
+/// inner query as derived table, to filter on its computed columns ex.: window function results
+/// ex.: `SELECT * FROM (<inner>) ranked`, then `BaseQuery::QueryBuilder` into `ConditionBuilder`
+pub fn from_subquery<'a>(inner: QueryBuilder<'a, Postgres>, alias: &str, ws: WhitespaceStyle) -> QueryBuilder<'a, Postgres> {
+    let nl = ws.newline();
+
+    wrap_query(&format!("SELECT *{0}FROM ({0}", nl), inner, &format!("{0}) {1}", nl, alias))
+}
+
+/// adds total row count of inner query to every row, to get a page and the total in one query
+/// ex.: `SELECT *, count(*) OVER () AS total FROM (<inner>) page`, LIMIT/OFFSET go after it
+pub fn with_total_count(inner: QueryBuilder<'_, Postgres>, ws: WhitespaceStyle) -> QueryBuilder<'_, Postgres> {
+    let nl = ws.newline();
+
+    wrap_query(&format!("SELECT *, count(*) OVER () AS total{0}FROM ({0}", nl), inner, &format!("{0}) page", nl))
+}
+
+/// zips set returning functions, function arguments are bound
+/// ex.: `SELECT * FROM ROWS FROM(unnest($1), unnest($2)) AS t(id, name)`
+pub fn from_rows_from<'a>(funcs: Vec<(&str, Vec<SqlValue>)>, alias: &str, columns: Vec<&str>, ws: WhitespaceStyle) -> QueryBuilder<'a, Postgres> {
+    let mut query: QueryBuilder<'a, Postgres> = QueryBuilder::new(format!("SELECT *{0}FROM ROWS FROM(", ws.newline()));
+
+    for (func_index, (func, args)) in funcs.into_iter().enumerate() {
+        if func_index > 0 {
+            query.push(", ");
+        }
+
+        query.push(format!("{0}(", func));
+
+        for (arg_index, arg) in args.into_iter().enumerate() {
+            if arg_index > 0 {
+                query.push(", ");
+            }
+
+            query = push_sqlvalue(arg, query);
+        }
+
+        query.push(")");
+    }
+
+    query.push(format!(") AS {0}", alias));
+
+    if !columns.is_empty() {
+        query.push(format!("({0})", columns.join(", ")));
+    }
+
+    query
+}
+
+/// every element of jsonb array column as a row, joined to its source row
+/// ex.: `SELECT orders.id, e.value FROM orders, jsonb_array_elements(orders.items) e`
+/// selected: columns of table selected alongside `alias.value`
+pub fn from_jsonb_array_elements<'a>(table: &str, column: &str, alias: &str, selected: Vec<&str>, ws: WhitespaceStyle) -> QueryBuilder<'a, Postgres> {
+    let mut columns: Vec<String> = selected.iter().map(|column| format!("{0}.{1}", table, column)).collect();
+    columns.push(format!("{0}.value", alias));
+
+    QueryBuilder::new(format!(
+        "SELECT {0}{1}FROM {2}, jsonb_array_elements({2}.{3}) {4}",
+        columns.join(", "),
+        ws.newline(),
+        table,
+        column,
+        alias
+    ))
+}
+
+/// line layout of sql rendered by builders and helpers ex.: `as_json_array`,
+/// sql given as is (base query, middle, end, last part, prefix and suffix of `wrap_query`) is not changed
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum WhitespaceStyle {
+    /// clauses on separate lines with indented conditions
+    #[default]
+    Pretty,
+    /// clauses on separate lines without indentation
+    Compact,
+    /// everything on one line, separated by single space
+    SingleLine,
+}
+
+impl WhitespaceStyle {
+    /// between clauses ex.: before `FROM`, `WHERE`
+    pub fn newline(&self) -> &'static str {
+        match self {
+            WhitespaceStyle::SingleLine => " ",
+            _ => "\n",
+        }
+    }
+
+    /// before conditions and SET columns ex.: `\n    AND a = $1`
+    pub fn indent(&self) -> &'static str {
+        match self {
+            WhitespaceStyle::Pretty => "\n    ",
+            WhitespaceStyle::Compact => "\n",
+            WhitespaceStyle::SingleLine => " ",
+        }
+    }
+
+    /// before each row of VALUES ex.: `VALUES\n       ($1, $2)`
+    pub fn row_indent(&self) -> &'static str {
+        match self {
+            WhitespaceStyle::Pretty => "\n       ",
+            other => other.indent(),
+        }
+    }
+
+    /// after statements which end with line break ex.: insert
+    pub fn line_end(&self) -> &'static str {
+        match self {
+            WhitespaceStyle::SingleLine => "",
+            _ => "\n",
+        }
+    }
+}
+
+/// values bound by `push_sqlvalue` in order, null is rendered as literal and records bind each field
+pub fn bound_values(value: &SqlValue) -> Vec<SqlValue> {
+    match value {
+        SqlValue::GenericValue(Value::Null) => Vec::new(),
+        SqlValue::Record { fields, .. } => fields.iter().flat_map(bound_values).collect(),
+        value => vec![value.clone()],
+    }
+}
+
+/// DEBUGGING AID ONLY, the result must never be executed
+/// placeholders after first skip ones are replaced by quoted literals of values in order,
+/// placeholders without value are kept ex.: `WHERE\n    name = 'O''Brien'`
+/// `$n` in quoted text, comments and dollar quoted bodies is not a placeholder and is kept
+pub fn preview_sql(sql: &str, skip: usize, values: &[SqlValue]) -> String {
+    let chars: Vec<char> = sql.chars().collect();
+    let mut preview = String::with_capacity(sql.len());
+    let mut copied = 0;
+
+    for (start, end, position) in placeholders(&chars) {
+        preview.extend(&chars[copied..start]);
+        copied = end;
+
+        let value = Some(position)
+            .filter(|position| *position > skip)
+            .and_then(|position| values.get(position - skip - 1));
+
+        match value {
+            Some(value) => preview.push_str(&sql_literal(value)),
+            None => preview.extend(&chars[start..end]),
+        }
+    }
+
+    preview.extend(&chars[copied..]);
+    preview
+}
+
+/// `$n` placeholders of sql as (start, end, n), char indexes, end is exclusive
+/// quoted text, quoted identifiers, comments and dollar quoted bodies are skipped
+fn placeholders(chars: &[char]) -> Vec<(usize, usize, usize)> {
+    let mut found: Vec<(usize, usize, usize)> = Vec::new();
+    let mut index = 0;
+
+    while index < chars.len() {
+        let next = chars.get(index + 1).copied();
+        let follows_ident = index > 0 && is_ident_char(chars[index - 1]);
+
+        let skip_to = match chars[index] {
+            '\'' => {
+                let escaped = index > 0
+                    && matches!(chars[index - 1], 'E' | 'e')
+                    && (index < 2 || !is_ident_char(chars[index - 2]));
+
+                Some(quoted_end(chars, index, '\'', escaped))
+            },
+            '"' => Some(quoted_end(chars, index, '"', false)),
+            '-' if next == Some('-') => Some(chars[index..].iter().position(|c| *c == '\n').map_or(chars.len(), |position| index + position)),
+            '/' if next == Some('*') => Some(block_comment_end(chars, index)),
+            '$' if !follows_ident && next.is_some_and(|c| c.is_ascii_digit()) => {
+                let end = index + 1 + chars[index + 1..].iter().take_while(|c| c.is_ascii_digit()).count();
+                let digits: String = chars[index + 1..end].iter().collect();
+
+                if let Ok(position) = digits.parse::<usize>() {
+                    found.push((index, end, position));
+                }
+
+                Some(end)
+            },
+            '$' => dollar_quoted_end(chars, index),
+            _ => None,
+        };
+
+        index = skip_to.unwrap_or(index + 1);
+    }
+
+    found
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// end (exclusive) of quoted text starting at start, doubled quote is escaped quote, backslash escapes if escaped
+fn quoted_end(chars: &[char], start: usize, quote: char, escaped: bool) -> usize {
+    let mut index = start + 1;
+
+    while index < chars.len() {
+        if (escaped && chars[index] == '\\') || (chars[index] == quote && chars.get(index + 1) == Some(&quote)) {
+            index += 2;
+        } else if chars[index] == quote {
+            return index + 1;
+        } else {
+            index += 1;
+        }
+    }
+
+    chars.len()
+}
+
+/// end (exclusive) of `/* */` comment starting at start, comments can be nested
+fn block_comment_end(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut index = start;
+
+    while index + 1 < chars.len() {
+        match (chars[index], chars[index + 1]) {
+            ('/', '*') => {
+                depth += 1;
+                index += 2;
+            },
+            ('*', '/') => {
+                depth -= 1;
+                index += 2;
+
+                if depth == 0 {
+                    return index;
+                }
+            },
+            _ => index += 1,
+        }
+    }
+
+    chars.len()
+}
+
+/// end (exclusive) of `$tag$..$tag$` starting at start, None if it is not a tag
+fn dollar_quoted_end(chars: &[char], start: usize) -> Option<usize> {
+    if start > 0 && is_ident_char(chars[start - 1]) {
+        return None;
+    }
+
+    let tag_len = chars[start + 1..].iter().take_while(|c| c.is_alphanumeric() || **c == '_').count();
+    let tag_end = start + 1 + tag_len;
+
+    if chars.get(start + 1).is_some_and(char::is_ascii_digit) || chars.get(tag_end) != Some(&'$') {
+        return None;
+    }
+
+    let tag = &chars[start..=tag_end];
+    let body_start = tag_end + 1;
+
+    let end = (body_start..chars.len())
+        .find(|index| chars[*index..].starts_with(tag))
+        .map_or(chars.len(), |index| index + tag.len());
+
+    Some(end)
+}
+
+pub(crate) fn sql_literal(value: &SqlValue) -> String {
+    let quote = |text: String| format!("'{0}'", text.replace('\'', "''"));
+
+    match value {
+        SqlValue::GenericValue(Value::Null) => "NULL".to_string(),
+        SqlValue::GenericValue(Value::Bool(v)) => v.to_string().to_uppercase(),
+        SqlValue::GenericValue(Value::Number(v)) => v.to_string(),
+        SqlValue::GenericValue(Value::String(v)) => quote(v.clone()),
+        SqlValue::GenericValue(v) => quote(v.to_string()),
+        SqlValue::NaiveChrono(NaiveChrono::NaiveDate(v)) => quote(v.to_string()),
+        SqlValue::NaiveChrono(NaiveChrono::NaiveDateTime(v)) => quote(v.to_string()),
+        SqlValue::NaiveChrono(NaiveChrono::DateTimeUtc(v)) => quote(v.to_rfc3339()),
+        SqlValue::NaiveChrono(NaiveChrono::DateTimeFixed(v)) => quote(v.to_rfc3339()),
+        SqlValue::Record { type_name, fields } => {
+            let fields: Vec<String> = fields.iter().map(sql_literal).collect();
+
+            format!("ROW({0})::{1}", fields.join(", "), type_name)
+        },
+        #[cfg(feature = "uuid")]
+        SqlValue::Uuid(v) => quote(v.to_string()),
+        #[cfg(feature = "uuid")]
+        SqlValue::UuidArray(v) => {
+            let items: Vec<String> = v.iter().map(Uuid::to_string).collect();
+
+            quote(format!("{{{0}}}", items.join(",")))
+        },
+        #[cfg(feature = "decimal")]
+        SqlValue::Decimal(v) => v.to_string(),
+    }
+}
+
+/// owned sql of a built query which can be cloned and bound again with new values
+/// ex.: `QueryTemplate::from_query(ConditionBuilder::new(..).build()).bind(vec![5.into()])`
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryTemplate {
+    pub sql: String,
+    /// number of values bound by the query, see `bind_count`
+    pub param_count: usize,
+}
+
+impl QueryTemplate {
+    /// bound values of query are dropped
+    pub fn from_query(query: QueryBuilder<'_, Postgres>) -> Self {
+        let (query, param_count) = bind_count(query);
+
+        Self { sql: query.into_sql(), param_count }
+    }
+
+    /// values are bound in placeholder order, None if they do not fill exactly param_count placeholders
+    /// (ex.: a null value is rendered as literal NULL and does not take a placeholder)
+    pub fn bind<'a>(&self, values: Vec<SqlValue>) -> Option<QueryBuilder<'a, Postgres>> {
+        let mut scratch: QueryBuilder<'a, Postgres> = QueryBuilder::new("");
+
+        for value in values {
+            scratch = push_sqlvalue(value, scratch);
+        }
+
+        let (mut scratch, count) = bind_count(scratch);
+
+        if count != self.param_count {
+            return None;
+        }
+
+        let arguments = scratch.build().take_arguments().unwrap_or_default();
+
+        Some(QueryBuilder::with_arguments(self.sql.clone(), arguments))
+    }
+}
+
+/// number of values bound to query, taken from its arguments, so `$n` in literals or comments is not counted
+/// query is given back as sqlx can only read its arguments by taking them
+pub fn bind_count<'a>(mut query: QueryBuilder<'a, Postgres>) -> (QueryBuilder<'a, Postgres>, usize) {
+    let mut built = query.build();
+    let sql = built.sql().to_string();
+    let arguments = built.take_arguments().unwrap_or_default();
+
+    // placeholder of the last bound value is `$<count>`
+    let mut last = String::new();
+    let count = match arguments.format_placeholder(&mut last) {
+        Ok(()) => last.trim_start_matches('$').parse::<usize>().unwrap_or_default(),
+        Err(_) => 0,
+    };
+
+    (QueryBuilder::with_arguments(sql, arguments), count)
+}
+
+/// highest `$n` placeholder of sql, 0 if there is none, `$n` in quoted text and comments is skipped
+/// count of a built query is `bind_count`
+pub fn max_placeholder(sql: &str) -> usize {
+    let chars: Vec<char> = sql.chars().collect();
+
+    placeholders(&chars)
+        .into_iter()
+        .map(|(_, _, position)| position)
+        .max()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{Number, Value, json};
+    use sqlx::{Postgres, QueryBuilder};
+
+    use crate::{BaseQuery, BoundNumber, Condition, ConditionBuilder, QueryTemplate, SqlValue, SqlValueError, WhitespaceStyle, as_json_array, from_jsonb_array_elements, from_rows_from, from_subquery, push_jsonvalue, quote_ident, quote_qualified, with_total_count};
+
+    #[test]
+    fn from_option_str() {
+        let some: Option<&str> = Some("sample");
+        let none: Option<&str> = None;
+
+        assert!(matches!(SqlValue::from(some), SqlValue::GenericValue(Value::String(v)) if v == "sample"));
+        assert!(matches!(SqlValue::from(none), SqlValue::GenericValue(Value::Null)));
+    }
+
+    #[test]
+    fn from_option_ref() {
+        let some: Option<i32> = Some(5);
+        let none: Option<i32> = None;
+        let name = "sample".to_string();
+        let some_string: Option<&String> = Some(&name);
+
+        assert!(matches!(SqlValue::from(&some), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(5)));
+        assert!(matches!(SqlValue::from(&none), SqlValue::GenericValue(Value::Null)));
+        assert!(matches!(SqlValue::from(some_string), SqlValue::GenericValue(Value::String(_))));
+    }
+
+    #[test]
+    fn json_array_keeps_binds() {
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let inner = ConditionBuilder::new(
+            BaseQuery::Sql("SELECT id, name\nFROM users"),
+            &conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .build();
+
+        let mut query = as_json_array(inner, WhitespaceStyle::Pretty);
+        query.push("\nLIMIT ").push_bind(1);
+
+        let result = "SELECT coalesce(json_agg(t), '[]')\nFROM (\nSELECT id, name\nFROM users\nWHERE\n    org_id = $1\n) t\nLIMIT $2";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn filter_window_result() {
+        let inner_conditions: Vec<Condition> = vec![Condition::new(None, "active", "=", true.into(), None)];
+        let inner = ConditionBuilder::new(
+            BaseQuery::Sql("SELECT *, row_number() OVER (PARTITION BY category ORDER BY score DESC) AS rn\nFROM products"),
+            &inner_conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .build();
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "rn", "<=", 3.into(), None)];
+        let query = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(from_subquery(inner, "ranked", WhitespaceStyle::Pretty)),
+            &conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .build();
+
+        let result = "SELECT *\nFROM (\nSELECT *, row_number() OVER (PARTITION BY category ORDER BY score DESC) AS rn\nFROM products\nWHERE\n    active = $1\n) ranked\nWHERE\n    rn <= $2";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn template_rebind() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "org_id", "=", 5.into(), None),
+            Condition::new(Some("AND"), "status", "=", "active".into(), None),
+        ];
+
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT id\nFROM users"), &conditions, None, None, None, None);
+        let template = QueryTemplate::from_query(query.build());
+        let cloned = template.clone();
+
+        let first = template.bind(vec![7.into(), "blocked".into()]).unwrap();
+        let second = cloned.bind(vec![8.into(), "active".into()]).unwrap();
+
+        assert_eq!(template.param_count, 2);
+        assert_eq!(first.sql(), "SELECT id\nFROM users\nWHERE\n    org_id = $1\n    AND status = $2");
+        assert_eq!(first.into_sql(), second.into_sql());
+        assert!(template.bind(vec![7.into()]).is_none());
+    }
+
+    #[test]
+    fn template_skips_placeholders_in_literals() {
+        let conditions: Vec<Condition> = vec![
+            Condition::new(None, "org_id", "=", 5.into(), None),
+            Condition::from_expression(Some("AND"), "note", "=", crate::Expression::new().sql("note <> '$3' /* $4 */")),
+        ];
+
+        let query = ConditionBuilder::new(BaseQuery::Sql("SELECT id\nFROM users"), &conditions, None, None, None, None);
+        let template = QueryTemplate::from_query(query.build());
+
+        assert_eq!(template.param_count, 1);
+        assert_eq!(template.bind(vec![7.into()]).unwrap().into_sql(), "SELECT id\nFROM users\nWHERE\n    org_id = $1\n    AND note <> '$3' /* $4 */");
+        assert!(template.bind(vec![7.into(), 8.into(), 9.into(), 10.into()]).is_none());
+    }
+
+    #[test]
+    fn rows_from_two_functions() {
+        let ids: Vec<i64> = vec![1, 2];
+        let names: Vec<&str> = vec!["a", "b"];
+        let source = from_rows_from(
+            vec![("unnest", vec![ids.into()]), ("unnest", vec![names.into()])],
+            "t",
+            vec!["id", "name"],
+            WhitespaceStyle::Pretty,
+        );
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "t.id", ">", 0.into(), None)];
+        let query = ConditionBuilder::new(BaseQuery::QueryBuilder(source), &conditions, None, None, None, None).build();
+
+        let result = "SELECT *\nFROM ROWS FROM(unnest($1), unnest($2)) AS t(id, name)\nWHERE\n    t.id > $3";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn page_with_total_count() {
+        let inner_conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+        let inner = ConditionBuilder::new(
+            BaseQuery::Sql("SELECT id, name\nFROM users"),
+            &inner_conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .build();
+
+        let conditions: Vec<Condition> = Vec::new();
+        let query = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(with_total_count(inner, WhitespaceStyle::Pretty)),
+            &conditions,
+            Some("ORDER BY name"),
+            Some(20),
+            Some(40),
+            None,
+        )
+        .build();
+
+        let result = "SELECT *, count(*) OVER () AS total\nFROM (\nSELECT id, name\nFROM users\nWHERE\n    org_id = $1\n) page\nORDER BY name\nLIMIT $2\nOFFSET $3";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn integer_binds_once() {
+        let query: QueryBuilder<'_, Postgres> = QueryBuilder::new("SELECT ");
+        let mut query = push_jsonvalue(json!(42), query);
+        query.push(", ").push_bind("next");
+
+        let sql = query.into_sql();
+
+        assert_eq!(sql.matches('$').count(), 2);
+        assert_eq!(sql, "SELECT $1, $2");
+        assert_eq!(BoundNumber::new(&Number::from(42)), BoundNumber::Integer(42));
+    }
+
+    #[test]
+    fn fractional_binds_once() {
+        let query: QueryBuilder<'_, Postgres> = QueryBuilder::new("SELECT ");
+        let mut query = push_jsonvalue(json!(10.05), query);
+        query.push(", ").push_bind("next");
+
+        let sql = query.into_sql();
+
+        assert_eq!(sql.matches('$').count(), 2);
+        assert_eq!(sql, "SELECT $1, $2");
+        assert_eq!(BoundNumber::new(&Number::from_f64(10.05).unwrap()), BoundNumber::Float(10.05));
+    }
+
+    #[test]
+    fn unsigned_above_bigint_binds_numeric() {
+        let query: QueryBuilder<'_, Postgres> = QueryBuilder::new("SELECT ");
+        let query = push_jsonvalue(json!(u64::MAX), query);
+
+        assert_eq!(query.into_sql(), "SELECT $1::numeric");
+        assert_eq!(BoundNumber::new(&Number::from(u64::MAX)), BoundNumber::Unsigned(u64::MAX));
+        assert!(matches!(SqlValue::from(u64::MAX), SqlValue::GenericValue(Value::Number(v)) if v.as_u64() == Some(u64::MAX)));
+    }
+
+    #[test]
+    fn from_unsigned() {
+        assert!(matches!(SqlValue::from(7u8), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(7)));
+        assert!(matches!(SqlValue::from(7u16), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(7)));
+        assert!(matches!(SqlValue::from(7u32), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(7)));
+        assert!(matches!(SqlValue::from(7usize), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(7)));
+        assert!(matches!(SqlValue::from(usize::MAX), SqlValue::GenericValue(Value::Number(v)) if v.as_i64() == Some(i64::MAX)));
+    }
+
+    #[test]
+    fn from_float() {
+        assert!(matches!(SqlValue::try_from(1.5f32), Ok(SqlValue::GenericValue(Value::Number(v))) if v.as_f64() == Some(1.5)));
+        assert!(matches!(SqlValue::try_from(10.05f64), Ok(SqlValue::GenericValue(Value::Number(v))) if v.as_f64() == Some(10.05)));
+        assert!(matches!(SqlValue::try_from(vec![0.5f32, 2.0]), Ok(SqlValue::GenericValue(Value::Array(v))) if v == vec![json!(0.5), json!(2.0)]));
+        assert!(matches!(SqlValue::try_from(vec![0.25f64]), Ok(SqlValue::GenericValue(Value::Array(v))) if v == vec![json!(0.25)]));
+    }
+
+    #[test]
+    fn from_non_finite_float() {
+        assert!(matches!(SqlValue::try_from(f64::NAN), Err(SqlValueError::NonFiniteFloat(v)) if v.is_nan()));
+        assert_eq!(SqlValue::try_from(f32::INFINITY).err(), Some(SqlValueError::NonFiniteFloat(f64::INFINITY)));
+        assert_eq!(SqlValue::try_from(vec![1.0, f64::NEG_INFINITY]).err(), Some(SqlValueError::NonFiniteFloat(f64::NEG_INFINITY)));
+        assert_eq!(SqlValueError::NonFiniteFloat(f64::INFINITY).to_string(), "float inf is not finite");
+    }
+
+    #[test]
+    fn jsonb_array_elements_rows() {
+        let source = from_jsonb_array_elements("orders", "items", "e", vec!["id"], WhitespaceStyle::Pretty);
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "orders.customer_id", "=", 5.into(), None)];
+        let query = ConditionBuilder::new(BaseQuery::QueryBuilder(source), &conditions, None, None, None, None).build();
+
+        let result = "SELECT orders.id, e.value\nFROM orders, jsonb_array_elements(orders.items) e\nWHERE\n    orders.customer_id = $1";
+
+        assert_eq!(query.into_sql(), result);
+    }
+
+    #[test]
+    fn helpers_single_line() {
+        let ws = WhitespaceStyle::SingleLine;
+        let inner = || QueryBuilder::<Postgres>::new("SELECT id FROM users");
+
+        assert_eq!(as_json_array(inner(), ws).into_sql(), "SELECT coalesce(json_agg(t), '[]') FROM ( SELECT id FROM users ) t");
+        assert_eq!(from_subquery(inner(), "ranked", ws).into_sql(), "SELECT * FROM ( SELECT id FROM users ) ranked");
+        assert_eq!(with_total_count(inner(), ws).into_sql(), "SELECT *, count(*) OVER () AS total FROM ( SELECT id FROM users ) page");
+        assert_eq!(
+            from_rows_from(vec![("unnest", vec![vec![1, 2].into()])], "t", vec!["id"], ws).into_sql(),
+            "SELECT * FROM ROWS FROM(unnest($1)) AS t(id)"
+        );
+        assert_eq!(
+            from_jsonb_array_elements("orders", "items", "e", vec!["id"], ws).into_sql(),
+            "SELECT orders.id, e.value FROM orders, jsonb_array_elements(orders.items) e"
+        );
+    }
+
+    #[test]
+    fn quoted_identifiers() {
+        let order = quote_ident("order");
+        let my_col = quote_ident("MyCol");
+        let columns: Vec<&str> = vec![&order, &my_col];
+        let rows: Vec<crate::Row> = vec![vec![Some(1.into()), Some(2.into())]];
+
+        let table = quote_qualified("public.User");
+        let insert_query = crate::InsertBuilder::new(&table, &columns, &rows, None);
+
+        assert_eq!(quote_ident("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(insert_query.build().into_sql(), "INSERT INTO \"public\".\"User\"(\"order\", \"MyCol\")\nVALUES\n       ($1, $2)\n");
     }
 }
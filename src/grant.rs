@@ -0,0 +1,130 @@
+use std::fmt;
+
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::is_valid_identifier;
+
+/// table privileges allowed in GRANT/REVOKE
+pub const TABLE_PRIVILEGES: [&str; 8] = [
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "TRUNCATE",
+    "REFERENCES",
+    "TRIGGER",
+    "ALL",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GrantError {
+    /// table or role is not a plain identifier
+    InvalidIdentifier(String),
+    /// privilege is not in `TABLE_PRIVILEGES`
+    InvalidPrivilege(String),
+    NoPrivileges,
+}
+
+impl fmt::Display for GrantError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GrantError::InvalidIdentifier(name) => write!(f, "`{name}` is not a valid identifier"),
+            GrantError::InvalidPrivilege(privilege) => write!(f, "`{privilege}` is not a table privilege"),
+            GrantError::NoPrivileges => write!(f, "no privileges provided"),
+        }
+    }
+}
+
+impl std::error::Error for GrantError {}
+
+/// `GRANT SELECT, INSERT ON table TO role` or `REVOKE SELECT ON table FROM role`
+#[derive(Debug, Clone)]
+pub struct GrantBuilder<'a> {
+    pub privileges: Vec<&'a str>,
+    pub table: &'a str,
+    pub role: &'a str,
+    pub revoke: bool,
+}
+
+impl<'a> GrantBuilder<'a> {
+    pub fn grant(privileges: Vec<&'a str>, table: &'a str, role: &'a str) -> Self {
+        Self {
+            privileges,
+            table,
+            role,
+            revoke: false,
+        }
+    }
+
+    pub fn revoke(privileges: Vec<&'a str>, table: &'a str, role: &'a str) -> Self {
+        Self {
+            privileges,
+            table,
+            role,
+            revoke: true,
+        }
+    }
+
+    pub fn build(self) -> Result<QueryBuilder<'a, Postgres>, GrantError> {
+        if self.privileges.is_empty() {
+            return Err(GrantError::NoPrivileges);
+        }
+
+        let mut privileges: Vec<String> = Vec::new();
+
+        for privilege in self.privileges.iter() {
+            let privilege = privilege.to_uppercase();
+
+            if !TABLE_PRIVILEGES.contains(&privilege.as_str()) {
+                return Err(GrantError::InvalidPrivilege(privilege));
+            }
+
+            privileges.push(privilege);
+        }
+
+        for name in [self.table, self.role] {
+            if !is_valid_identifier(name) {
+                return Err(GrantError::InvalidIdentifier(name.to_string()));
+            }
+        }
+
+        let sql = if self.revoke {
+            format!("REVOKE {0} ON {1} FROM {2}", privileges.join(", "), self.table, self.role)
+        } else {
+            format!("GRANT {0} ON {1} TO {2}", privileges.join(", "), self.table, self.role)
+        };
+
+        Ok(QueryBuilder::new(sql))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{GrantBuilder, GrantError};
+
+    #[test]
+    fn grant_multiple_privileges() {
+        let grant = GrantBuilder::grant(vec!["select", "INSERT"], "tenant_a.users", "tenant_a_rw");
+
+        assert_eq!(grant.build().unwrap().into_sql(), "GRANT SELECT, INSERT ON tenant_a.users TO tenant_a_rw");
+    }
+
+    #[test]
+    fn revoke_privilege() {
+        let revoke = GrantBuilder::revoke(vec!["INSERT"], "users", "tenant_a_rw");
+
+        assert_eq!(revoke.build().unwrap().into_sql(), "REVOKE INSERT ON users FROM tenant_a_rw");
+    }
+
+    #[test]
+    fn grant_invalid_input() {
+        let bad_role = GrantBuilder::grant(vec!["SELECT"], "users", "x; DROP TABLE users");
+        let bad_privilege = GrantBuilder::grant(vec!["SELECT", "EXECUTE"], "users", "reader");
+
+        assert_eq!(
+            bad_role.build().err(),
+            Some(GrantError::InvalidIdentifier("x; DROP TABLE users".to_string()))
+        );
+        assert_eq!(bad_privilege.build().err(), Some(GrantError::InvalidPrivilege("EXECUTE".to_string())));
+    }
+}
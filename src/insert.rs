@@ -1,16 +1,83 @@
 
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
 use serde_json::Value;
 use sqlx::{Postgres, QueryBuilder};
 
-use crate::{SqlValue, NaiveChrono};
+use crate::{BaseQuery, BoundNumber, Condition, ConditionBuilder, SqlValue, NaiveChrono, PartitionError, bound_values, UpdColumn, WhitespaceStyle, preview_sql, push_sqlvalue, push_updcolumn, tag_comment, wrap_query};
 
 pub type Row = Vec<Option<SqlValue>>;
 
+#[derive(Debug, Clone, PartialEq)]
+pub enum InsertError {
+    /// row has other number of values than there are columns
+    RowArity { row_index: usize, expected: usize, got: usize },
+    /// `build_if_not_exists` inserts exactly one row
+    SingleRowExpected { got: usize },
+    /// `default` is only valid in VALUES, not in SELECT of `build_if_not_exists`
+    DefaultInSelect { column: String },
+    /// `ON CONFLICT DO UPDATE` needs a conflict target
+    MissingConflictTarget,
+    /// dedup key index is not a column index
+    KeyIndexOutOfRange { index: usize, columns: usize },
+}
+
+impl fmt::Display for InsertError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InsertError::RowArity { row_index, expected, got } => {
+                write!(f, "row {row_index} has {got} values, expected {expected}")
+            },
+            InsertError::SingleRowExpected { got } => write!(f, "expected exactly one row, got {got}"),
+            InsertError::DefaultInSelect { column } => write!(f, "column {column} has no value, default is not allowed in select"),
+            InsertError::MissingConflictTarget => write!(f, "ON CONFLICT DO UPDATE requires a conflict target"),
+            InsertError::KeyIndexOutOfRange { index, columns } => write!(f, "key index {index} is out of range for {columns} columns"),
+        }
+    }
+}
+
+impl std::error::Error for InsertError {}
+
+/// which of rows with the same key is inserted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DedupKeep {
+    First,
+    Last,
+}
+
+/// target of `ON CONFLICT`
+#[derive(Debug, Clone)]
+pub enum ConflictTarget<'a> {
+    /// `ON CONFLICT (org_id, email)`, nothing is rendered after `ON CONFLICT` if empty
+    Columns(Vec<&'a str>),
+    /// unique expression index ex.: `ON CONFLICT ((lower(email)))`
+    Expression(&'a str),
+}
+
+/// action of `ON CONFLICT`, see `on_conflict`
+#[derive(Debug, Clone)]
+pub enum ConflictAction<'a> {
+    DoNothing,
+    /// empty columns update every inserted column outside of target ex.: `DO UPDATE SET name = EXCLUDED.name`,
+    /// `DO NOTHING` if there is no such column, otherwise given columns are set, values are bound,
+    /// expressions can combine target and EXCLUDED ex.: `SET count = counters.count + EXCLUDED.count`
+    DoUpdate(Vec<UpdColumn<'a>>),
+}
+
 pub struct InsertBuilder<'a> {
     pub table: &'a str,
     pub columns: &'a Vec<&'a str>,
     pub rows: &'a Vec<Row>,
     pub last_part: Option<&'a str>,
+    /// rendered as trailing comment, see `tag_comment`
+    pub tag: Option<BTreeMap<&'a str, &'a str>>,
+    /// key column indexes, see `dedup_by`
+    pub dedup: Option<(Vec<usize>, DedupKeep)>,
+    /// conflict target and action, see `on_conflict`
+    pub on_conflict: Option<(ConflictTarget<'a>, ConflictAction<'a>)>,
+    /// line layout of built queries, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
 }
 
 impl<'a> InsertBuilder<'a> {
@@ -25,84 +92,447 @@ impl<'a> InsertBuilder<'a> {
             columns,
             rows,
             last_part,
+            tag: None,
+            dedup: None,
+            on_conflict: None,
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    pub fn tag(mut self, tag: BTreeMap<&'a str, &'a str>) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// skips rows with the same values at key_indices as another row,
+    /// avoids `ON CONFLICT DO UPDATE command cannot affect row a second time`
+    /// error if a key index is not an index of columns
+    pub fn dedup_by(mut self, key_indices: &[usize], keep: DedupKeep) -> Result<Self, InsertError> {
+        if let Some(index) = key_indices.iter().find(|index| **index >= self.columns.len()) {
+            return Err(InsertError::KeyIndexOutOfRange { index: *index, columns: self.columns.len() });
         }
+
+        self.dedup = Some((key_indices.to_vec(), keep));
+        Ok(self)
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    /// rendered after VALUES ex.: `ON CONFLICT (id) DO NOTHING`, empty target is rendered as `ON CONFLICT`
+    /// error for empty target with `DoUpdate`, postgres requires a target there
+    /// target of a `NULLS NOT DISTINCT` unique index (postgres 15) is the same column list, rows with
+    /// nulls in nullable target columns then conflict as well
+    pub fn on_conflict(mut self, target: Vec<&'a str>, action: ConflictAction<'a>) -> Result<Self, InsertError> {
+        if target.is_empty() && matches!(action, ConflictAction::DoUpdate(_)) {
+            return Err(InsertError::MissingConflictTarget);
+        }
+
+        self.on_conflict = Some((ConflictTarget::Columns(target), action));
+        Ok(self)
     }
 
+    /// conflict on unique expression index ex.: `ON CONFLICT ((lower(email))) DO NOTHING`
+    pub fn on_conflict_expression(mut self, expression: &'a str, action: ConflictAction<'a>) -> Self {
+        self.on_conflict = Some((ConflictTarget::Expression(expression), action));
+        self
+    }
+
+    /// inserts and returns number of inserted rows in the same query
+    /// ex.: `WITH ins AS (INSERT ... RETURNING *) SELECT count(*) FROM ins`
+    /// `RETURNING *` is added if last_part has no RETURNING ex.: `ON CONFLICT DO NOTHING RETURNING *`
+    /// `SELECT 0::bigint` if no row is inserted ex.: all rows have other number of values than columns
+    pub fn build_count(mut self) -> QueryBuilder<'a, Postgres> {
+        if self.valid_rows().is_empty() {
+            return QueryBuilder::new("SELECT 0::bigint");
+        }
+
+        let nl = self.whitespace.newline();
+        let tag = self.tag.take();
+        let returning = self.last_part.is_some_and(|last_part| last_part.to_uppercase().contains("RETURNING"));
+        let rows = self.valid_rows();
+        let mut inserted = self.push_rows(self.table, &rows);
+
+        if !returning {
+            inserted.push(format!("{0}RETURNING *", nl));
+        }
+
+        if let Some(tag) = &tag {
+            inserted.push(format!("{0}{1}", nl, tag_comment(tag)));
+        }
+
+        wrap_query(&format!("WITH ins AS ({0}", nl), inserted, &format!("{0}){0}SELECT count(*) FROM ins", nl))
+    }
+
+    /// rows with other number of values than columns are skipped, see `try_build`
     pub fn build(self) -> QueryBuilder<'a, Postgres> {
-        let mut query: QueryBuilder<'_, Postgres> = QueryBuilder::new("");
+        let rows = self.valid_rows();
 
-        if self.rows.is_empty() {
-            return query;
+        self.build_rows(self.table, &rows)
+    }
+
+    /// same as build, but returns error for first row with other number of values than columns
+    pub fn try_build(self) -> Result<QueryBuilder<'a, Postgres>, InsertError> {
+        let expected = self.columns.len();
+
+        if let Some((row_index, row)) = self.rows.iter().enumerate().find(|(_, row)| row.len() != expected) {
+            return Err(InsertError::RowArity { row_index, expected, got: row.len() });
         }
 
-        query.push(format!("INSERT INTO {0}(", self.table));
+        Ok(self.build())
+    }
+
+    /// one insert per partition, rows grouped by value at key_index in order of first appearance
+    /// ex.: `insert.partition_by(0, |key| Partition::new("events", PartitionScheme::Monthly).name(key))`
+    pub fn partition_by<F>(&self, key_index: usize, naming: F) -> Result<Vec<QueryBuilder<'a, Postgres>>, PartitionError>
+    where
+        F: Fn(&SqlValue) -> Result<String, PartitionError>,
+    {
+        let mut partitions: Vec<(String, Vec<&'a Row>)> = Vec::new();
+
+        for row in self.selected_rows() {
+            if self.columns.len() != row.len() {
+                continue;
+            }
+
+            let key = match row.get(key_index) {
+                Some(Some(key)) => key,
+                _ => return Err(PartitionError::UnsupportedKey),
+            };
+            let table = naming(key)?;
 
-        for (index, column) in self.columns.iter().enumerate() {
-            if index < self.columns.len() - 1 {
-                query.push(format!("{0}, ", *column));
-            } else {
-                query.push(format!("{0})\n", *column));
+            match partitions.iter_mut().find(|(name, _)| *name == table) {
+                Some((_, rows)) => rows.push(row),
+                None => partitions.push((table, vec![row])),
             }
         }
 
-        query.push("VALUES\n");
+        Ok(partitions
+            .iter()
+            .map(|(table, rows)| self.build_rows(table, rows))
+            .collect())
+    }
 
-        for (row_index, row) in self.rows.iter().enumerate() {
-            if self.columns.len() == (*row).len() {
-                query.push("       (");
+    /// inserts single row only if no row matches conditions, for tables without unique constraint
+    /// ex.: `INSERT INTO t(a) SELECT $1 WHERE NOT EXISTS (SELECT 1 FROM t WHERE a = $2)`
+    /// error if there is not exactly one row or row has None (default) value, which is invalid in SELECT
+    pub fn build_if_not_exists(self, conditions: &'a Vec<Condition<'a>>) -> Result<QueryBuilder<'a, Postgres>, InsertError> {
+        let row = match self.selected_rows()[..] {
+            [row] => row,
+            ref rows => return Err(InsertError::SingleRowExpected { got: rows.len() }),
+        };
+        let expected = self.columns.len();
+
+        if row.len() != expected {
+            return Err(InsertError::RowArity { row_index: 0, expected, got: row.len() });
+        }
 
-                for (col_index, value) in (*row).iter().enumerate() {
-                    match value {
-                        Some(sql_value) => match sql_value {
-                            SqlValue::GenericValue(Value::Null) => {
-                                query.push("null");
-                            },
-                            SqlValue::GenericValue(Value::Bool(v)) => { query.push_bind(v); },
-                            SqlValue::GenericValue(Value::Number(v)) => {
-                                if v.is_i64() || v.is_u64() {
-                                    query.push_bind(v.as_i64().unwrap());
-                                } else {
-                                    query.push_bind(v.as_f64().unwrap());
-                                }
-                            },
-                            SqlValue::GenericValue(Value::String(v)) => { query.push_bind(v); },
-                            SqlValue::GenericValue(Value::Array(v)) => { query.push_bind(v); },
-                            SqlValue::GenericValue(Value::Object(_)) => {
-                                if let SqlValue::GenericValue(val) = sql_value {
-                                    query.push_bind(val);
-                                }
-                            }
-                            SqlValue::NaiveChrono(naive_chrono) => {
-                                match naive_chrono {
-                                    NaiveChrono::NaiveDate(chrono_value) => {
-                                        query.push_bind(chrono_value);
-                                    },
-                                    NaiveChrono::NaiveDateTime(chrono_value) => {
-                                        query.push_bind(chrono_value);
-                                    },
-                                }
-                            },
-                        },
-                        None => {
-                            query.push("default");
-                        }
-                    }
+        if let Some(index) = row.iter().position(Option::is_none) {
+            return Err(InsertError::DefaultInSelect { column: self.columns[index].to_string() });
+        }
+
+        let nl = self.whitespace.newline();
+        let mut query: QueryBuilder<'a, Postgres> = QueryBuilder::new(self.insert_into(self.table));
+        query.push(format!("{0}SELECT ", nl));
+        query = Self::push_row(row, query);
+        query.push(format!("{0}WHERE NOT EXISTS ({0}SELECT 1{0}FROM {1}", nl, self.table));
+
+        let mut query = ConditionBuilder::new(BaseQuery::QueryBuilder(query), conditions, None, None, None, None)
+            .whitespace(self.whitespace)
+            .build();
+        query.push(format!("{0})", nl));
+
+        if let Some(last_part) = self.last_part {
+            query.push(format!("{0}{1}", nl, last_part));
+        }
+
+        if let Some(tag) = &self.tag {
+            query.push(format!("{0}{1}", nl, tag_comment(tag)));
+        }
+
+        query.push(self.whitespace.line_end());
+
+        Ok(query)
+    }
+
+    /// number of parameters of `build` without building it, null and default cells are not bound
+    /// postgres allows at most 65535, so large inserts should be split, see `build_chunked`
+    pub fn param_count(&self) -> usize {
+        let cells: usize = self
+            .selected_rows()
+            .iter()
+            .filter(|row| row.len() == self.columns.len())
+            .map(|row| Self::row_param_count(row))
+            .sum();
+
+        cells + self.conflict_param_count()
+    }
+
+    /// DEBUGGING AID ONLY, `build` with bound values inlined as literals, see `preview_sql`
+    pub fn preview_sql(self) -> String {
+        let mut values: Vec<SqlValue> = self
+            .selected_rows()
+            .iter()
+            .filter(|row| row.len() == self.columns.len())
+            .flat_map(|row| row.iter().flatten().flat_map(bound_values))
+            .collect();
+        values.extend(self.conflict_values());
+
+        preview_sql(&self.build().into_sql(), 0, &values)
+    }
+
+    /// one insert per chunk of rows, each with at most max_params parameters and its own last_part,
+    /// a row which alone exceeds max_params gets its own chunk
+    pub fn build_chunked(&self, max_params: usize) -> Vec<QueryBuilder<'a, Postgres>> {
+        let conflict_params = self.conflict_param_count();
+        let mut chunks: Vec<Vec<&'a Row>> = Vec::new();
+        let mut chunk_params = conflict_params;
+
+        for row in self.selected_rows() {
+            if row.len() != self.columns.len() {
+                continue;
+            }
+
+            let row_params = Self::row_param_count(row);
+
+            match chunks.last_mut() {
+                Some(chunk) if chunk_params + row_params <= max_params => {
+                    chunk.push(row);
+                    chunk_params += row_params;
+                },
+                _ => {
+                    chunks.push(vec![row]);
+                    chunk_params = conflict_params + row_params;
+                },
+            }
+        }
+
+        chunks
+            .iter()
+            .map(|rows| self.build_rows(self.table, rows))
+            .collect()
+    }
+
+    fn row_param_count(row: &Row) -> usize {
+        row.iter().flatten().map(|value| bound_values(value).len()).sum()
+    }
+
+    fn conflict_param_count(&self) -> usize {
+        self.conflict_values().len()
+    }
+
+    fn conflict_values(&self) -> Vec<SqlValue> {
+        match &self.on_conflict {
+            Some((_, ConflictAction::DoUpdate(columns))) => columns.iter().flat_map(|(_, value)| value.bound_values()).collect(),
+            _ => Vec::new(),
+        }
+    }
 
-                    if col_index < (*row).len() - 1 {
+    fn push_on_conflict(
+        &self,
+        target: &ConflictTarget<'a>,
+        action: &ConflictAction<'a>,
+        mut query: QueryBuilder<'a, Postgres>,
+    ) -> QueryBuilder<'a, Postgres> {
+        let target: &[&str] = match target {
+            ConflictTarget::Columns(columns) if columns.is_empty() => {
+                query.push("ON CONFLICT");
+                columns
+            },
+            ConflictTarget::Columns(columns) => {
+                query.push(format!("ON CONFLICT ({0})", columns.join(", ")));
+                columns
+            },
+            ConflictTarget::Expression(expression) => {
+                query.push(format!("ON CONFLICT (({0}))", expression));
+                &[]
+            },
+        };
+
+        match action {
+            ConflictAction::DoNothing => {
+                query.push(" DO NOTHING");
+            },
+            ConflictAction::DoUpdate(columns) if columns.is_empty() => {
+                let excluded: Vec<String> = self
+                    .columns
+                    .iter()
+                    .filter(|column| !target.contains(column))
+                    .map(|column| format!("{0} = EXCLUDED.{0}", column))
+                    .collect();
+
+                if excluded.is_empty() {
+                    query.push(" DO NOTHING");
+                } else {
+                    query.push(format!(" DO UPDATE SET {0}", excluded.join(", ")));
+                }
+            },
+            ConflictAction::DoUpdate(columns) => {
+                query.push(" DO UPDATE SET ");
+
+                for (index, (column, value)) in columns.iter().enumerate() {
+                    if index > 0 {
                         query.push(", ");
                     }
-                }
 
-                if row_index < self.rows.len() - 1 {
-                    query.push("),\n");
-                } else {
-                    query.push(")\n");
+                    query.push(format!("{0} = ", column));
+                    query = push_updcolumn(value.clone(), query);
                 }
+            },
+        }
+
+        query
+    }
+
+    fn selected_rows(&self) -> Vec<&'a Row> {
+        let (key_indices, keep) = match &self.dedup {
+            Some(dedup) => dedup,
+            None => return self.rows.iter().collect(),
+        };
+
+        let mut keys: HashSet<String> = HashSet::new();
+        let mut rows: Vec<&'a Row> = Vec::new();
+
+        let ordered: Box<dyn Iterator<Item = &'a Row>> = match keep {
+            DedupKeep::First => Box::new(self.rows.iter()),
+            DedupKeep::Last => Box::new(self.rows.iter().rev()),
+        };
+
+        for row in ordered {
+            // rows with other number of values are skipped on build, they must not hide a valid row with the same key
+            if row.len() != self.columns.len() {
+                rows.push(row);
+                continue;
+            }
+
+            let key_values: Vec<&Option<SqlValue>> = key_indices.iter().map(|index| &row[*index]).collect();
+            let key = serde_json::to_string(&key_values).unwrap_or_default();
+
+            if keys.insert(key) {
+                rows.push(row);
             }
         }
 
+        if *keep == DedupKeep::Last {
+            rows.reverse();
+        }
+
+        rows
+    }
+
+    /// selected rows with as many values as columns, others are skipped by build
+    fn valid_rows(&self) -> Vec<&'a Row> {
+        self.selected_rows()
+            .into_iter()
+            .filter(|row| row.len() == self.columns.len())
+            .collect()
+    }
+
+    /// rows must have as many values as columns, see `valid_rows`
+    fn build_rows(&self, table: &str, rows: &[&'a Row]) -> QueryBuilder<'a, Postgres> {
+        if rows.is_empty() {
+            return QueryBuilder::new("");
+        }
+
+        let mut query = self.push_rows(table, rows);
+        query.push(self.whitespace.line_end());
+
+        query
+    }
+
+    /// insert without line end after last part, rows must not be empty
+    fn push_rows(&self, table: &str, rows: &[&'a Row]) -> QueryBuilder<'a, Postgres> {
+        let nl = self.whitespace.newline();
+        let row_indent = self.whitespace.row_indent();
+
+        let mut query: QueryBuilder<'_, Postgres> = QueryBuilder::new(self.insert_into(table));
+        query.push(format!("{0}VALUES", nl));
+
+        for (row_index, row) in rows.iter().enumerate() {
+            if row_index > 0 {
+                query.push(",");
+            }
+
+            query.push(format!("{0}(", row_indent));
+            query = Self::push_row(row, query);
+            query.push(")");
+        }
+
+        if let Some((target, action)) = &self.on_conflict {
+            query.push(nl);
+            query = self.push_on_conflict(target, action, query);
+        }
+
         if let Some(last_part) = self.last_part {
-            query.push(format!("{0}\n", last_part));
+            query.push(format!("{0}{1}", nl, last_part));
+        }
+
+        if let Some(tag) = &self.tag {
+            query.push(format!("{0}{1}", nl, tag_comment(tag)));
+        }
+
+        query
+    }
+
+    fn insert_into(&self, table: &str) -> String {
+        format!("INSERT INTO {0}({1})", table, self.columns.join(", "))
+    }
+
+    /// values of row separated by comma, `default` for None
+    fn push_row(row: &'a Row, mut query: QueryBuilder<'a, Postgres>) -> QueryBuilder<'a, Postgres> {
+        for (col_index, value) in row.iter().enumerate() {
+            match value {
+                Some(sql_value) => match sql_value {
+                    SqlValue::GenericValue(Value::Null) => {
+                        query.push("null");
+                    },
+                    SqlValue::GenericValue(Value::Bool(v)) => { query.push_bind(v); },
+                    SqlValue::GenericValue(Value::Number(v)) => {
+                        query = BoundNumber::new(v).push(query);
+                    },
+                    SqlValue::GenericValue(Value::String(v)) => { query.push_bind(v); },
+                    SqlValue::GenericValue(Value::Array(v)) => { query.push_bind(v); },
+                    SqlValue::GenericValue(Value::Object(_)) => {
+                        if let SqlValue::GenericValue(val) = sql_value {
+                            query.push_bind(val);
+                        }
+                    }
+                    SqlValue::NaiveChrono(naive_chrono) => {
+                        match naive_chrono {
+                            NaiveChrono::NaiveDate(chrono_value) => {
+                                query.push_bind(chrono_value);
+                            },
+                            NaiveChrono::NaiveDateTime(chrono_value) => {
+                                query.push_bind(chrono_value);
+                            },
+                            NaiveChrono::DateTimeUtc(chrono_value) => {
+                                query.push_bind(chrono_value);
+                            },
+                            NaiveChrono::DateTimeFixed(chrono_value) => {
+                                query.push_bind(chrono_value);
+                            },
+                        }
+                    },
+                    SqlValue::Record { .. } => {
+                        query = push_sqlvalue(sql_value.clone(), query);
+                    },
+                    #[cfg(feature = "uuid")]
+                    SqlValue::Uuid(v) => { query.push_bind(v); },
+                    #[cfg(feature = "uuid")]
+                    SqlValue::UuidArray(v) => { query.push_bind(v); },
+                    #[cfg(feature = "decimal")]
+                    SqlValue::Decimal(v) => { query.push_bind(v); },
+                },
+                None => {
+                    query.push("default");
+                }
+            }
+
+            if col_index < row.len() - 1 {
+                query.push(", ");
+            }
         }
 
         query
@@ -111,11 +541,15 @@ impl<'a> InsertBuilder<'a> {
 
 #[cfg(test)]
 mod tests {
-    use crate::{InsertBuilder, Row};
+    use std::collections::BTreeMap;
+
+    use chrono::{DateTime, NaiveDate, Utc};
+
+    use crate::{Condition, ConflictAction, DedupKeep, Expression, InsertBuilder, InsertError, WhitespaceStyle, Partition, PartitionScheme, Row, SqlValue};
 
     #[test]
-    fn insert_one_column_one_row<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_one_column_one_row() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut row1: Row = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
 
@@ -130,8 +564,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_one_column_two_rows<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_one_column_two_rows() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut row1: Row = Vec::new();
         let mut row2: Row = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
@@ -149,8 +583,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_two_column_one_row<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_two_column_one_row() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut row1: Row = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
 
@@ -167,8 +601,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_three_column_multi_rows<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_three_column_multi_rows() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut row1: Row = Vec::new();
         let mut row2: Row = Vec::new();
         let mut row3: Row = Vec::new();
@@ -208,8 +642,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_three_column_multi_rows_with_wrong_rows<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_three_column_multi_rows_with_wrong_rows() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
 
         let mut row1: Row = Vec::new();
@@ -247,8 +681,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_three_column_multi_rows_with_none_values<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_three_column_multi_rows_with_none_values() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
 
         let mut row1: Row = Vec::new();
@@ -288,8 +722,8 @@ mod tests {
     }
 
     #[test]
-    fn insert_three_column_two_rows_with_last_part<'a>() {
-        let mut columns: Vec<&'a str> = Vec::new();
+    fn insert_three_column_two_rows_with_last_part() {
+        let mut columns: Vec<&str> = Vec::new();
         let mut rows: Vec<Row> = Vec::new();
 
         let mut row1: Row = Vec::new();
@@ -316,4 +750,467 @@ mod tests {
 
         assert_eq!(insert_query.build().into_sql(), result);
     }
+
+    #[test]
+    fn insert_with_tag() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut row1: Row = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("column1");
+        row1.push(Some("title1".into()));
+        rows.push(row1);
+
+        let insert_query = InsertBuilder::new("sample_table", &columns, &rows, Some("RETURNING id"))
+            .tag(BTreeMap::from([("job", "import")]));
+        let result = "INSERT INTO sample_table(column1)\nVALUES\n       ($1)\nRETURNING id\n/* job=import */\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_with_count() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("column1");
+        rows.push(vec![Some("title1".into())]);
+        rows.push(vec![Some("title2".into())]);
+
+        let insert_query = InsertBuilder::new("sample_table", &columns, &rows, None);
+        let result = "WITH ins AS (\nINSERT INTO sample_table(column1)\nVALUES\n       ($1),\n       ($2)\nRETURNING *\n)\nSELECT count(*) FROM ins";
+
+        assert_eq!(insert_query.build_count().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_with_count_without_returning() {
+        let columns: Vec<&str> = vec!["id"];
+        let rows: Vec<Row> = vec![vec![Some(1.into())], vec![Some(2.into())]];
+
+        let insert_query = InsertBuilder::new("sample_table", &columns, &rows, Some("ON CONFLICT DO NOTHING"));
+        let returning_query = InsertBuilder::new("sample_table", &columns, &rows, Some("ON CONFLICT DO NOTHING returning id"));
+        let result = "WITH ins AS (\nINSERT INTO sample_table(id)\nVALUES\n       ($1),\n       ($2)\nON CONFLICT DO NOTHING\nRETURNING *\n)\nSELECT count(*) FROM ins";
+        let returning_result = "WITH ins AS (\nINSERT INTO sample_table(id)\nVALUES\n       ($1),\n       ($2)\nON CONFLICT DO NOTHING returning id\n)\nSELECT count(*) FROM ins";
+
+        assert_eq!(insert_query.build_count().into_sql(), result);
+        assert_eq!(returning_query.build_count().into_sql(), returning_result);
+    }
+
+    #[test]
+    fn insert_with_count_without_valid_rows() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into())], vec![Some(2.into()), Some("b".into()), Some("extra".into())]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, None);
+
+        assert_eq!(insert_query.build_count().into_sql(), "SELECT 0::bigint");
+    }
+
+    #[test]
+    fn insert_last_row_skipped() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())], vec![Some(2.into())]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, None);
+
+        assert_eq!(insert_query.build().into_sql(), "INSERT INTO users(id, name)\nVALUES\n       ($1, $2)\n");
+    }
+
+    #[test]
+    fn insert_partition_by() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("created_at");
+        columns.push("title");
+        rows.push(vec![Some(NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().into()), Some("title1".into())]);
+        rows.push(vec![Some(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap().into()), Some("title2".into())]);
+        rows.push(vec![Some(NaiveDate::from_ymd_opt(2024, 1, 20).unwrap().into()), Some("title3".into())]);
+
+        let partition = Partition::new("events", PartitionScheme::Monthly);
+        let insert_query = InsertBuilder::new("events", &columns, &rows, None);
+        let queries: Vec<String> = insert_query
+            .partition_by(0, |key| partition.name(key))
+            .unwrap()
+            .into_iter()
+            .map(|query| query.into_sql())
+            .collect();
+
+        assert_eq!(
+            queries,
+            vec![
+                "INSERT INTO events_2024_01(created_at, title)\nVALUES\n       ($1, $2),\n       ($3, $4)\n",
+                "INSERT INTO events_2024_02(created_at, title)\nVALUES\n       ($1, $2)\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn insert_record_value() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("name");
+        columns.push("address");
+
+        let address = SqlValue::Record {
+            type_name: "address".to_string(),
+            fields: vec!["Main St 1".into(), "Springfield".into()],
+        };
+        rows.push(vec![Some("office".into()), Some(address)]);
+
+        let insert_query = InsertBuilder::new("locations", &columns, &rows, None);
+        let result = "INSERT INTO locations(name, address)\nVALUES\n       ($1, ROW($2, $3)::address)\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_dedup_by_key() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("sku");
+        columns.push("price");
+        rows.push(vec![Some("A-1".into()), Some(10.into())]);
+        rows.push(vec![Some("B-2".into()), Some(20.into())]);
+        rows.push(vec![Some("A-1".into()), Some(12.into())]);
+
+        let upsert = Some("ON CONFLICT (sku) DO UPDATE SET price = EXCLUDED.price");
+        let first_query = InsertBuilder::new("products", &columns, &rows, upsert).dedup_by(&[0], DedupKeep::First).unwrap();
+        let last_query = InsertBuilder::new("products", &columns, &rows, upsert).dedup_by(&[0], DedupKeep::Last).unwrap();
+        let result = "INSERT INTO products(sku, price)\nVALUES\n       ($1, $2),\n       ($3, $4)\nON CONFLICT (sku) DO UPDATE SET price = EXCLUDED.price\n";
+
+        let prices = |query: &InsertBuilder| -> String {
+            let rows: Vec<&Row> = query.selected_rows();
+            serde_json::to_string(&rows.iter().map(|row| &row[1]).collect::<Vec<_>>()).unwrap()
+        };
+
+        assert_eq!(prices(&first_query), r#"[{"GenericValue":10},{"GenericValue":20}]"#);
+        assert_eq!(prices(&last_query), r#"[{"GenericValue":20},{"GenericValue":12}]"#);
+        assert_eq!(first_query.build().into_sql(), result);
+        assert_eq!(last_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_dedup_invalid_key() {
+        let columns: Vec<&str> = vec!["sku", "price"];
+        let rows: Vec<Row> = vec![
+            vec![Some("A-1".into()), Some(10.into())],
+            vec![Some("A-1".into())],
+            vec![Some("A-1".into()), Some(12.into())],
+        ];
+
+        let out_of_range = InsertBuilder::new("products", &columns, &rows, None).dedup_by(&[5], DedupKeep::First);
+        let short_row_query = InsertBuilder::new("products", &columns, &rows, None).dedup_by(&[0], DedupKeep::Last).unwrap();
+
+        assert_eq!(out_of_range.err(), Some(InsertError::KeyIndexOutOfRange { index: 5, columns: 2 }));
+        assert_eq!(short_row_query.build().into_sql(), "INSERT INTO products(sku, price)\nVALUES\n       ($1, $2)\n");
+    }
+
+    #[test]
+    fn insert_if_not_exists() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+        let mut conditions: Vec<Condition> = Vec::new();
+
+        columns.push("email");
+        columns.push("name");
+        rows.push(vec![Some("a@b.c".into()), Some("Ann".into())]);
+        conditions.push(Condition::ci_eq(None, "email", "a@b.c".into()));
+
+        let insert_query = InsertBuilder::new("subscribers", &columns, &rows, Some("RETURNING id"));
+        let result = "INSERT INTO subscribers(email, name)\nSELECT $1, $2\nWHERE NOT EXISTS (\nSELECT 1\nFROM subscribers\nWHERE\n    lower(email) = lower($3)\n)\nRETURNING id\n";
+
+        assert_eq!(insert_query.build_if_not_exists(&conditions).unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_if_not_exists_invalid_rows() {
+        let columns: Vec<&str> = vec!["email", "name"];
+        let conditions: Vec<Condition> = vec![Condition::eq("email", "a@b.c")];
+        let two_rows: Vec<Row> = vec![vec![Some("a@b.c".into()), Some("Ann".into())], vec![Some("d@e.f".into()), Some("Dan".into())]];
+        let default_cell: Vec<Row> = vec![vec![Some("a@b.c".into()), None]];
+
+        let two_rows_query = InsertBuilder::new("subscribers", &columns, &two_rows, None);
+        let default_query = InsertBuilder::new("subscribers", &columns, &default_cell, None);
+
+        assert_eq!(two_rows_query.build_if_not_exists(&conditions).err(), Some(InsertError::SingleRowExpected { got: 2 }));
+        assert_eq!(default_query.build_if_not_exists(&conditions).err(), Some(InsertError::DefaultInSelect { column: "name".to_string() }));
+    }
+
+    #[test]
+    fn insert_timestamptz() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("title");
+        columns.push("happened_at");
+        columns.push("reported_at");
+
+        let happened_at = DateTime::parse_from_rfc3339("2024-01-15T10:30:00+02:00").unwrap();
+        rows.push(vec![Some("title1".into()), Some(happened_at.into()), Some(happened_at.with_timezone(&Utc).into())]);
+
+        let insert_query = InsertBuilder::new("events", &columns, &rows, None);
+        let result = "INSERT INTO events(title, happened_at, reported_at)\nVALUES\n       ($1, $2, $3)\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+        assert_eq!(
+            serde_json::to_string(&rows[0][1]).unwrap(),
+            r#"{"NaiveChrono":{"DateTimeFixed":"2024-01-15T10:30:00+02:00"}}"#
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "uuid")]
+    fn insert_uuid() {
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("id");
+        columns.push("related_ids");
+
+        let id = uuid::Uuid::parse_str("67e55044-10b1-426f-9247-bb680e5fe0c8").unwrap();
+        rows.push(vec![Some(id.into()), Some(vec![id].into())]);
+
+        let insert_query = InsertBuilder::new("documents", &columns, &rows, None);
+        let result = "INSERT INTO documents(id, related_ids)\nVALUES\n       ($1, $2)\n";
+
+        assert!(matches!(&rows[0][0], Some(SqlValue::Uuid(v)) if *v == id));
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    #[cfg(feature = "decimal")]
+    fn insert_decimal() {
+        use std::str::FromStr;
+
+        let mut columns: Vec<&str> = Vec::new();
+        let mut rows: Vec<Row> = Vec::new();
+
+        columns.push("title");
+        columns.push("price");
+
+        let price = rust_decimal::Decimal::from_str("10.05").unwrap();
+        rows.push(vec![Some("coffee".into()), Some(price.into())]);
+
+        let insert_query = InsertBuilder::new("products", &columns, &rows, None);
+        let result = "INSERT INTO products(title, price)\nVALUES\n       ($1, $2)\n";
+
+        assert!(matches!(&rows[0][1], Some(SqlValue::Decimal(v)) if v.to_string() == "10.05"));
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_on_conflict_do_nothing() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, Some("RETURNING id"))
+            .on_conflict(vec!["id"], ConflictAction::DoNothing)
+            .unwrap();
+        let result = "INSERT INTO users(id, name)\nVALUES\n       ($1, $2)\nON CONFLICT (id) DO NOTHING\nRETURNING id\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_on_conflict_do_update() {
+        let columns: Vec<&str> = vec!["org_id", "email", "name", "visits"];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some("a@b.c".into()), Some("a".into()), Some(1.into())],
+            vec![Some(1.into()), Some("d@e.f".into()), Some("d".into()), Some(1.into())],
+        ];
+
+        let excluded_query = InsertBuilder::new("members", &columns, &rows, None)
+            .on_conflict(vec!["org_id", "email"], ConflictAction::DoUpdate(Vec::new()))
+            .unwrap();
+        let excluded_result = "INSERT INTO members(org_id, email, name, visits)\nVALUES\n       ($1, $2, $3, $4),\n       ($5, $6, $7, $8)\nON CONFLICT (org_id, email) DO UPDATE SET name = EXCLUDED.name, visits = EXCLUDED.visits\n";
+
+        let explicit_query = InsertBuilder::new("members", &columns, &rows, None).on_conflict(
+            vec!["org_id", "email"],
+            ConflictAction::DoUpdate(vec![
+                ("name", "renamed".into()),
+                ("visits", Expression::new().sql("members.visits + ").value(1).into()),
+            ]),
+        ).unwrap();
+        let explicit_result = "INSERT INTO members(org_id, email, name, visits)\nVALUES\n       ($1, $2, $3, $4),\n       ($5, $6, $7, $8)\nON CONFLICT (org_id, email) DO UPDATE SET name = $9, visits = members.visits + $10\n";
+
+        assert_eq!(excluded_query.build().into_sql(), excluded_result);
+        assert_eq!(explicit_query.build().into_sql(), explicit_result);
+    }
+
+    #[test]
+    fn insert_on_conflict_accumulate() {
+        let columns: Vec<&str> = vec!["id", "count"];
+        let rows: Vec<Row> = vec![vec![Some("page-1".into()), Some(3.into())]];
+
+        let insert_query = InsertBuilder::new("counters", &columns, &rows, None)
+            .on_conflict(
+                vec!["id"],
+                ConflictAction::DoUpdate(vec![("count", Expression::new().sql("counters.count + EXCLUDED.count").into())]),
+            )
+            .unwrap();
+        let result = "INSERT INTO counters(id, count)\nVALUES\n       ($1, $2)\nON CONFLICT (id) DO UPDATE SET count = counters.count + EXCLUDED.count\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_on_conflict_nullable_target() {
+        let columns: Vec<&str> = vec!["org_id", "external_ref", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some(serde_json::Value::Null.into()), Some("a".into())]];
+
+        let insert_query = InsertBuilder::new("accounts", &columns, &rows, None)
+            .on_conflict(vec!["org_id", "external_ref"], ConflictAction::DoUpdate(Vec::new()))
+            .unwrap();
+        let result = "INSERT INTO accounts(org_id, external_ref, name)\nVALUES\n       ($1, null, $2)\nON CONFLICT (org_id, external_ref) DO UPDATE SET name = EXCLUDED.name\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_on_conflict_without_target() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())]];
+
+        let nothing_query = InsertBuilder::new("users", &columns, &rows, None)
+            .on_conflict(Vec::new(), ConflictAction::DoNothing)
+            .unwrap();
+        let update_query = InsertBuilder::new("users", &columns, &rows, None)
+            .on_conflict(Vec::new(), ConflictAction::DoUpdate(Vec::new()));
+
+        assert_eq!(nothing_query.build().into_sql(), "INSERT INTO users(id, name)\nVALUES\n       ($1, $2)\nON CONFLICT DO NOTHING\n");
+        assert_eq!(update_query.err(), Some(InsertError::MissingConflictTarget));
+    }
+
+    #[test]
+    fn insert_on_conflict_expression() {
+        let columns: Vec<&str> = vec!["email", "name"];
+        let rows: Vec<Row> = vec![vec![Some("A@b.c".into()), Some("a".into())]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, None)
+            .on_conflict_expression("lower(email)", ConflictAction::DoUpdate(vec![("name", "renamed".into())]));
+        let result = "INSERT INTO users(email, name)\nVALUES\n       ($1, $2)\nON CONFLICT ((lower(email))) DO UPDATE SET name = $3\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_param_count() {
+        let columns: Vec<&str> = vec!["id", "name", "note"];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some("a".into()), None],
+            vec![Some(2.into()), Some(serde_json::Value::Null.into()), Some("n".into())],
+            vec![Some(3.into())],
+        ];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, None)
+            .on_conflict(vec!["id"], ConflictAction::DoUpdate(vec![("name", "renamed".into())]))
+            .unwrap();
+        let param_count = insert_query.param_count();
+
+        assert_eq!(param_count, 5);
+        assert_eq!(crate::max_placeholder(&insert_query.build().into_sql()), param_count);
+    }
+
+    #[test]
+    fn insert_chunked() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = (1..=5).map(|id| vec![Some(id.into()), Some(format!("user{id}").into())]).collect();
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, Some("ON CONFLICT DO NOTHING"));
+        let chunks: Vec<String> = insert_query.build_chunked(4).into_iter().map(|chunk| chunk.into_sql()).collect();
+
+        assert_eq!(
+            chunks,
+            vec![
+                "INSERT INTO users(id, name)\nVALUES\n       ($1, $2),\n       ($3, $4)\nON CONFLICT DO NOTHING\n",
+                "INSERT INTO users(id, name)\nVALUES\n       ($1, $2),\n       ($3, $4)\nON CONFLICT DO NOTHING\n",
+                "INSERT INTO users(id, name)\nVALUES\n       ($1, $2)\nON CONFLICT DO NOTHING\n",
+            ]
+        );
+        assert_eq!(insert_query.build_chunked(65535).len(), 1);
+    }
+
+    #[test]
+    fn insert_try_build_ok() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())], vec![Some(2.into()), None]];
+
+        let result = "INSERT INTO users(id, name)\nVALUES\n       ($1, $2),\n       ($3, default)\n";
+
+        assert_eq!(InsertBuilder::new("users", &columns, &rows, None).try_build().unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_try_build_row_arity() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some("a".into())],
+            vec![Some(2.into())],
+            vec![Some(3.into()), Some("c".into()), Some("extra".into())],
+        ];
+
+        let error = InsertBuilder::new("users", &columns, &rows, None).try_build().err();
+
+        assert_eq!(error, Some(InsertError::RowArity { row_index: 1, expected: 2, got: 1 }));
+    }
+
+    #[test]
+    fn insert_single_line() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())], vec![Some(2.into()), None]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, Some("RETURNING id"))
+            .whitespace(WhitespaceStyle::SingleLine);
+
+        assert_eq!(insert_query.build().into_sql(), "INSERT INTO users(id, name) VALUES ($1, $2), ($3, default) RETURNING id");
+    }
+
+    #[test]
+    fn insert_count_single_line() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some("a".into())]];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, None)
+            .on_conflict(vec!["id"], ConflictAction::DoNothing)
+            .unwrap()
+            .whitespace(WhitespaceStyle::SingleLine);
+
+        let result = "WITH ins AS ( INSERT INTO users(id, name) VALUES ($1, $2) ON CONFLICT (id) DO NOTHING RETURNING * ) SELECT count(*) FROM ins";
+
+        assert_eq!(insert_query.build_count().into_sql(), result);
+    }
+
+    #[test]
+    fn insert_unsigned_above_bigint() {
+        let columns: Vec<&str> = vec!["id", "size"];
+        let rows: Vec<Row> = vec![vec![Some(1.into()), Some(SqlValue::GenericValue(serde_json::json!(u64::MAX)))]];
+
+        let insert_query = InsertBuilder::new("blobs", &columns, &rows, None);
+
+        assert_eq!(insert_query.build().into_sql(), "INSERT INTO blobs(id, size)\nVALUES\n       ($1, $2::numeric)\n");
+
+        let insert_query = InsertBuilder::new("blobs", &columns, &rows, None);
+
+        assert_eq!(insert_query.preview_sql(), "INSERT INTO blobs(id, size)\nVALUES\n       (1, 18446744073709551615::numeric)\n");
+    }
+
+    #[test]
+    fn insert_preview() {
+        let columns: Vec<&str> = vec!["id", "name", "note"];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some("O'Brien".into()), None],
+            vec![Some(2.into()), Some(serde_json::Value::Null.into()), Some("n".into())],
+        ];
+
+        let insert_query = InsertBuilder::new("users", &columns, &rows, Some("RETURNING id"))
+            .on_conflict(vec!["id"], ConflictAction::DoUpdate(vec![("name", "renamed".into())]))
+            .unwrap();
+        let result = "INSERT INTO users(id, name, note)\nVALUES\n       (1, 'O''Brien', default),\n       (2, null, 'n')\nON CONFLICT (id) DO UPDATE SET name = 'renamed'\nRETURNING id\n";
+
+        assert_eq!(insert_query.preview_sql(), result);
+    }
 }
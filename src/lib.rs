@@ -1,9 +1,29 @@
+pub mod batch;
+pub mod comment;
 pub mod condition;
+pub mod cte;
+pub mod cursor;
+pub mod delete;
+pub mod expression;
 pub mod general;
+pub mod grant;
 pub mod insert;
+pub mod partition;
+pub mod select;
+pub mod temp_table;
 pub mod update;
 
+pub use batch::*;
+pub use comment::*;
 pub use condition::*;
+pub use cte::*;
+pub use cursor::*;
+pub use delete::*;
+pub use expression::*;
 pub use general::*;
+pub use grant::*;
 pub use insert::*;
+pub use partition::*;
+pub use select::*;
+pub use temp_table::*;
 pub use update::*;
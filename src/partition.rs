@@ -0,0 +1,129 @@
+use std::fmt;
+
+use chrono::{Datelike, NaiveDate};
+use serde_json::Value;
+
+use crate::{NaiveChrono, SqlValue, is_valid_identifier};
+
+/// naming scheme of partitions, parent table name used as prefix
+#[derive(Debug, Clone, Copy)]
+pub enum PartitionScheme {
+    /// `events_2024`
+    Yearly,
+    /// `events_2024_01`
+    Monthly,
+    /// `events_2024_01_15`
+    Daily,
+    /// list partitions suffixed by key value ex.: `events_eu`
+    List,
+    /// gets parent table and partition key, returns partition name
+    Custom(fn(&str, &SqlValue) -> Option<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartitionError {
+    /// key value can not be used with the naming scheme
+    UnsupportedKey,
+    /// computed name is not a valid identifier
+    InvalidName(String),
+}
+
+impl fmt::Display for PartitionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionError::UnsupportedKey => write!(f, "partition key is not supported by the naming scheme"),
+            PartitionError::InvalidName(name) => write!(f, "`{name}` is not a valid partition name"),
+        }
+    }
+}
+
+impl std::error::Error for PartitionError {}
+
+/// computes partition name of parent table for a partition key value
+/// ex.: `Partition::new("events", PartitionScheme::Monthly)` gives `events_2024_01` for `2024-01-15`
+#[derive(Debug, Clone, Copy)]
+pub struct Partition<'a> {
+    pub parent: &'a str,
+    pub scheme: PartitionScheme,
+}
+
+impl<'a> Partition<'a> {
+    pub fn new(parent: &'a str, scheme: PartitionScheme) -> Self {
+        Self { parent, scheme }
+    }
+
+    pub fn name(&self, key: &SqlValue) -> Result<String, PartitionError> {
+        let name = match self.scheme {
+            PartitionScheme::Yearly => {
+                let date = Self::key_date(key)?;
+                format!("{0}_{1:04}", self.parent, date.year())
+            },
+            PartitionScheme::Monthly => {
+                let date = Self::key_date(key)?;
+                format!("{0}_{1:04}_{2:02}", self.parent, date.year(), date.month())
+            },
+            PartitionScheme::Daily => {
+                let date = Self::key_date(key)?;
+                format!("{0}_{1:04}_{2:02}_{3:02}", self.parent, date.year(), date.month(), date.day())
+            },
+            PartitionScheme::List => match key {
+                SqlValue::GenericValue(Value::String(v)) => format!("{0}_{1}", self.parent, v),
+                SqlValue::GenericValue(Value::Number(v)) => format!("{0}_{1}", self.parent, v),
+                _ => return Err(PartitionError::UnsupportedKey),
+            },
+            PartitionScheme::Custom(naming) => {
+                naming(self.parent, key).ok_or(PartitionError::UnsupportedKey)?
+            },
+        };
+
+        if !is_valid_identifier(&name) || name.chars().any(|c| c.is_ascii_uppercase()) {
+            return Err(PartitionError::InvalidName(name));
+        }
+
+        Ok(name)
+    }
+
+    fn key_date(key: &SqlValue) -> Result<NaiveDate, PartitionError> {
+        match key {
+            SqlValue::NaiveChrono(NaiveChrono::NaiveDate(v)) => Ok(*v),
+            SqlValue::NaiveChrono(NaiveChrono::NaiveDateTime(v)) => Ok(v.date()),
+            // timezone aware values are partitioned by their UTC date
+            SqlValue::NaiveChrono(NaiveChrono::DateTimeUtc(v)) => Ok(v.date_naive()),
+            SqlValue::NaiveChrono(NaiveChrono::DateTimeFixed(v)) => Ok(v.naive_utc().date()),
+            _ => Err(PartitionError::UnsupportedKey),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{InsertBuilder, Partition, PartitionError, PartitionScheme, Row, SqlValue};
+
+    #[test]
+    fn monthly_partition_insert() {
+        let key: SqlValue = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap().into();
+        let table = Partition::new("events", PartitionScheme::Monthly).name(&key).unwrap();
+
+        let columns: Vec<&str> = vec!["created_at", "title"];
+        let rows: Vec<Row> = vec![vec![Some(key), Some("title1".into())]];
+
+        let insert_query = InsertBuilder::new(&table, &columns, &rows, None);
+        let result = "INSERT INTO events_2024_01(created_at, title)\nVALUES\n       ($1, $2)\n";
+
+        assert_eq!(insert_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn list_partition_name() {
+        let partition = Partition::new("public.events", PartitionScheme::List);
+
+        assert_eq!(partition.name(&"eu".into()), Ok("public.events_eu".to_string()));
+        assert_eq!(
+            partition.name(&"eu; DROP TABLE x".into()),
+            Err(PartitionError::InvalidName("public.events_eu; DROP TABLE x".to_string()))
+        );
+        assert_eq!(partition.name(&true.into()), Err(PartitionError::UnsupportedKey));
+    }
+}
@@ -0,0 +1,723 @@
+use std::fmt;
+
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{Condition, ConditionBuilder, BaseQuery, OrderBy, SqlValue, WhitespaceStyle, is_valid_identifier, sql_literal, wrap_query};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectError {
+    /// column of `USING` is not a plain identifier
+    InvalidIdentifier(String),
+    /// `USING` without columns
+    EmptyUsing,
+    /// TABLESAMPLE percent is not finite or outside of 0..=100
+    InvalidSamplePercent(f64),
+    /// percentile fraction is not finite or outside of 0..=1
+    InvalidFraction(f64),
+    /// `string_agg` needs a separator, other aggregates take none
+    InvalidSeparator,
+    /// column references a window which is not defined by `window`
+    UnknownWindow(String),
+}
+
+impl fmt::Display for SelectError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectError::InvalidIdentifier(name) => write!(f, "`{name}` is not a valid identifier"),
+            SelectError::EmptyUsing => write!(f, "join USING has no columns"),
+            SelectError::InvalidSamplePercent(percent) => write!(f, "`{percent}` is not a sample percent between 0 and 100"),
+            SelectError::InvalidFraction(fraction) => write!(f, "`{fraction}` is not a fraction between 0 and 1"),
+            SelectError::InvalidSeparator => write!(f, "separator is required by string_agg and only by it"),
+            SelectError::UnknownWindow(name) => write!(f, "window `{name}` is not defined"),
+        }
+    }
+}
+
+impl std::error::Error for SelectError {}
+
+/// orders result of inner query differently than inner ORDER BY ex.: latest row per group by DISTINCT ON
+/// ex.: `SELECT * FROM (<inner>) sub ORDER BY total DESC`, bound values of inner query are kept
+pub fn reorder<'a>(inner: QueryBuilder<'a, Postgres>, order_by: &str, ws: WhitespaceStyle) -> QueryBuilder<'a, Postgres> {
+    let nl = ws.newline();
+
+    wrap_query(&format!("SELECT *{0}FROM ({0}", nl), inner, &format!("{0}) sub{0}ORDER BY {1}", nl, order_by))
+}
+
+/// ordered-set aggregate functions, fraction is rendered as literal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderedSetFunc {
+    /// interpolated percentile ex.: `percentile_cont(0.95)`
+    PercentileCont(f64),
+    /// first value at or above percentile ex.: `percentile_disc(0.5)`
+    PercentileDisc(f64),
+    /// most frequent value
+    Mode,
+}
+
+impl OrderedSetFunc {
+    pub fn as_sql(&self) -> String {
+        match self {
+            OrderedSetFunc::PercentileCont(fraction) => format!("percentile_cont({0})", fraction),
+            OrderedSetFunc::PercentileDisc(fraction) => format!("percentile_disc({0})", fraction),
+            OrderedSetFunc::Mode => "mode()".to_string(),
+        }
+    }
+
+    fn fraction(&self) -> Option<f64> {
+        match self {
+            OrderedSetFunc::PercentileCont(fraction) | OrderedSetFunc::PercentileDisc(fraction) => Some(*fraction),
+            OrderedSetFunc::Mode => None,
+        }
+    }
+}
+
+/// aggregate functions collecting values of a group
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AggFunc {
+    ArrayAgg,
+    /// needs separator
+    StringAgg,
+    JsonAgg,
+}
+
+impl AggFunc {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            AggFunc::ArrayAgg => "array_agg",
+            AggFunc::StringAgg => "string_agg",
+            AggFunc::JsonAgg => "json_agg",
+        }
+    }
+}
+
+/// frame of a named window ex.: `PARTITION BY customer_id ORDER BY created_at ASC`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowSpec<'a> {
+    pub partition_by: Vec<&'a str>,
+    pub order_by: OrderBy<'a>,
+}
+
+impl<'a> WindowSpec<'a> {
+    pub fn new(partition_by: Vec<&'a str>, order_by: OrderBy<'a>) -> Self {
+        Self { partition_by, order_by }
+    }
+
+    pub fn as_sql(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+
+        if !self.partition_by.is_empty() {
+            parts.push(format!("PARTITION BY {0}", self.partition_by.join(", ")));
+        }
+
+        if !self.order_by.is_empty() {
+            let terms: Vec<String> = self.order_by.iter().map(|(column, direction)| format!("{0} {1}", column, direction.as_sql())).collect();
+            parts.push(format!("ORDER BY {0}", terms.join(", ")));
+        }
+
+        parts.join(" ")
+    }
+}
+
+/// `WINDOW w AS (..), w2 AS (..)`, empty if there is no window
+pub fn window_clause(windows: &[(&str, WindowSpec)]) -> String {
+    let definitions: Vec<String> = windows.iter().map(|(name, spec)| format!("{0} AS ({1})", name, spec.as_sql())).collect();
+
+    if definitions.is_empty() {
+        return String::new();
+    }
+
+    format!("WINDOW {0}", definitions.join(", "))
+}
+
+/// selected column, see `SelectBuilder::column`
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectColumn<'a> {
+    /// rendered as is ex.: `id`, `count(*) AS total`
+    Raw(&'a str),
+    /// separator is rendered as escaped literal ex.: `array_agg(name ORDER BY name) AS names`, `string_agg(DISTINCT tag, ',')`
+    Agg {
+        func: AggFunc,
+        arg: &'a str,
+        order_by: Option<&'a str>,
+        distinct: bool,
+        separator: Option<&'a str>,
+        alias: Option<&'a str>,
+    },
+    /// fraction is rendered as literal ex.: `percentile_cont(0.95) WITHIN GROUP (ORDER BY latency) AS p95`
+    OrderedSetAgg {
+        func: OrderedSetFunc,
+        order_by: &'a str,
+        alias: &'a str,
+    },
+    /// window function over a window named by `SelectBuilder::window` ex.: `sum(amount) OVER w AS running`
+    Window {
+        func: &'a str,
+        window: &'a str,
+        alias: Option<&'a str>,
+    },
+}
+
+impl<'a> SelectColumn<'a> {
+    fn validate(&self) -> Result<(), SelectError> {
+        match self {
+            SelectColumn::Raw(_) => Ok(()),
+            SelectColumn::Agg { func, separator, .. } => {
+                if (*func == AggFunc::StringAgg) != separator.is_some() {
+                    return Err(SelectError::InvalidSeparator);
+                }
+
+                Ok(())
+            },
+            SelectColumn::OrderedSetAgg { func, .. } => match func.fraction() {
+                Some(fraction) if !(0.0..=1.0).contains(&fraction) => Err(SelectError::InvalidFraction(fraction)),
+                _ => Ok(()),
+            },
+            SelectColumn::Window { window, .. } if !is_valid_identifier(window) => Err(SelectError::InvalidIdentifier(window.to_string())),
+            SelectColumn::Window { .. } => Ok(()),
+        }
+    }
+
+    fn push(&self, mut query: QueryBuilder<'a, Postgres>) -> QueryBuilder<'a, Postgres> {
+        match self {
+            SelectColumn::Raw(column) => {
+                query.push(*column);
+            },
+            SelectColumn::Agg { func, arg, order_by, distinct, separator, alias } => {
+                query.push(format!("{0}({1}{2}", func.as_sql(), if *distinct { "DISTINCT " } else { "" }, arg));
+
+                if let Some(separator) = separator {
+                    query.push(format!(", {0}", sql_literal(&SqlValue::from(*separator))));
+                }
+
+                if let Some(order_by) = order_by {
+                    query.push(format!(" ORDER BY {0}", order_by));
+                }
+
+                query.push(")");
+
+                if let Some(alias) = alias {
+                    query.push(format!(" AS {0}", alias));
+                }
+            },
+            SelectColumn::OrderedSetAgg { func, order_by, alias } => {
+                query.push(format!("{0} WITHIN GROUP (ORDER BY {1}) AS {2}", func.as_sql(), order_by, alias));
+            },
+            SelectColumn::Window { func, window, alias } => {
+                query.push(format!("{0} OVER {1}", func, window));
+
+                if let Some(alias) = alias {
+                    query.push(format!(" AS {0}", alias));
+                }
+            },
+        }
+
+        query
+    }
+}
+
+/// kind of JOIN clause
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Right,
+    Full,
+}
+
+impl JoinKind {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            JoinKind::Inner => "INNER JOIN",
+            JoinKind::Left => "LEFT JOIN",
+            JoinKind::Right => "RIGHT JOIN",
+            JoinKind::Full => "FULL JOIN",
+        }
+    }
+}
+
+/// TABLESAMPLE method, SYSTEM samples pages, BERNOULLI samples rows
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleMethod {
+    System,
+    Bernoulli,
+}
+
+impl SampleMethod {
+    pub fn as_sql(&self) -> &'static str {
+        match self {
+            SampleMethod::System => "SYSTEM",
+            SampleMethod::Bernoulli => "BERNOULLI",
+        }
+    }
+}
+
+/// sampled table for FROM or join, alias goes before TABLESAMPLE, percent is rendered as literal
+/// ex.: `events e TABLESAMPLE SYSTEM (1)`, error if percent is not within 0..=100
+pub fn tablesample(table: &str, alias: Option<&str>, method: SampleMethod, percent: f64) -> Result<String, SelectError> {
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(SelectError::InvalidSamplePercent(percent));
+    }
+
+    let table = match alias {
+        Some(alias) => format!("{0} {1}", table, alias),
+        None => table.to_string(),
+    };
+
+    Ok(format!("{0} TABLESAMPLE {1} ({2})", table, method.as_sql(), percent))
+}
+
+/// join condition
+#[derive(Debug, Clone, PartialEq)]
+pub enum JoinOn<'a> {
+    /// rendered as is ex.: `ON orders.user_id = users.id`
+    On(&'a str),
+    /// columns of the same name in both tables, output has them once ex.: `USING (id, org_id)`
+    Using(Vec<&'a str>),
+}
+
+impl<'a> JoinOn<'a> {
+    pub fn as_sql(&self) -> String {
+        match self {
+            JoinOn::On(on) => format!("ON {0}", on),
+            JoinOn::Using(columns) => format!("USING ({0})", columns.join(", ")),
+        }
+    }
+}
+
+/// builds `SELECT .. FROM ..` part of a query, conditions are added by `ConditionBuilder`
+/// ex.: `ConditionBuilder::new(BaseQuery::QueryBuilder(select.build()), &conditions, ..)`
+#[derive(Debug, Clone)]
+pub struct SelectBuilder<'a> {
+    pub table: &'a str,
+    /// tables after table in FROM, comma separated, join predicate goes to conditions
+    /// ex.: `FROM orders o, customers c` with condition `o.customer_id = c.id`
+    pub from_tables: Vec<&'a str>,
+    /// selected columns, `*` if empty, see `column`
+    pub columns: Vec<SelectColumn<'a>>,
+    /// `SELECT DISTINCT ..`, ignored if distinct_on is not empty
+    pub distinct: bool,
+    /// `SELECT DISTINCT ON (customer_id) ..`, ORDER BY must start with these columns,
+    /// matching it is left to the caller
+    pub distinct_on: Vec<&'a str>,
+    /// rendered after FROM in order of adding, see `join`
+    pub joins: Vec<(JoinKind, &'a str, JoinOn<'a>)>,
+    /// named windows, rendered after WHERE, GROUP BY and HAVING, see `with_conditions`
+    pub windows: Vec<(&'a str, WindowSpec<'a>)>,
+    /// line layout of built query, passed to `ConditionBuilder` by `with_conditions`, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
+}
+
+impl<'a> SelectBuilder<'a> {
+    pub fn new(table: &'a str, columns: Vec<&'a str>) -> Self {
+        Self {
+            table,
+            from_tables: Vec::new(),
+            columns: columns.into_iter().map(SelectColumn::Raw).collect(),
+            distinct: false,
+            distinct_on: Vec::new(),
+            joins: Vec::new(),
+            windows: Vec::new(),
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    /// adds column after the given ones, error for string_agg without separator or fraction outside of 0..=1
+    pub fn column(mut self, column: SelectColumn<'a>) -> Result<Self, SelectError> {
+        column.validate()?;
+        self.columns.push(column);
+        Ok(self)
+    }
+
+    pub fn from_tables(mut self, tables: Vec<&'a str>) -> Self {
+        self.from_tables = tables;
+        self
+    }
+
+    pub fn distinct(mut self) -> Self {
+        self.distinct = true;
+        self
+    }
+
+    pub fn distinct_on(mut self, columns: Vec<&'a str>) -> Self {
+        self.distinct_on = columns;
+        self
+    }
+
+    /// table and on are rendered as is ex.: `LEFT JOIN orders ON orders.user_id = users.id`,
+    /// table can be schema qualified and aliased ex.: `shared.roles r`
+    pub fn join(mut self, kind: JoinKind, table: &'a str, on: &'a str) -> Self {
+        self.joins.push((kind, table, JoinOn::On(on)));
+        self
+    }
+
+    /// ex.: `JOIN memberships USING (user_id, org_id)`, error if columns are empty or not plain identifiers
+    pub fn join_using(mut self, kind: JoinKind, table: &'a str, columns: Vec<&'a str>) -> Result<Self, SelectError> {
+        if columns.is_empty() {
+            return Err(SelectError::EmptyUsing);
+        }
+
+        if let Some(column) = columns.iter().find(|column| !is_valid_identifier(column)) {
+            return Err(SelectError::InvalidIdentifier(column.to_string()));
+        }
+
+        self.joins.push((kind, table, JoinOn::Using(columns)));
+        Ok(self)
+    }
+
+    /// defines named window for `SelectColumn::Window` columns, error if name is not a plain identifier
+    pub fn window(mut self, name: &'a str, spec: WindowSpec<'a>) -> Result<Self, SelectError> {
+        if !is_valid_identifier(name) {
+            return Err(SelectError::InvalidIdentifier(name.to_string()));
+        }
+
+        self.windows.push((name, spec));
+        Ok(self)
+    }
+
+    /// built select as base query of conditions, windows are rendered by `ConditionBuilder` after HAVING
+    /// error if a window column references a window which is not defined
+    pub fn with_conditions(self, conditions: &'a Vec<Condition<'a>>) -> Result<ConditionBuilder<'a>, SelectError> {
+        let undefined = self.columns.iter().find_map(|column| match column {
+            SelectColumn::Window { window, .. } if !self.windows.iter().any(|(name, _)| name == window) => Some(*window),
+            _ => None,
+        });
+
+        if let Some(window) = undefined {
+            return Err(SelectError::UnknownWindow(window.to_string()));
+        }
+
+        let windows = self.windows.clone();
+
+        Ok(ConditionBuilder::new(BaseQuery::QueryBuilder(self.build()), conditions, None, None, None, None)
+            .windows(windows)
+            .whitespace(self.whitespace))
+    }
+
+    /// `SELECT .. FROM ..` without WINDOW clause, see `with_conditions`
+    pub fn build(&self) -> QueryBuilder<'a, Postgres> {
+        let mut query: QueryBuilder<'a, Postgres> = QueryBuilder::new("SELECT ");
+
+        if !self.distinct_on.is_empty() {
+            query.push(format!("DISTINCT ON ({0}) ", self.distinct_on.join(", ")));
+        } else if self.distinct {
+            query.push("DISTINCT ");
+        }
+
+        if self.columns.is_empty() {
+            query.push("*");
+        }
+
+        for (index, column) in self.columns.iter().enumerate() {
+            if index > 0 {
+                query.push(", ");
+            }
+
+            query = column.push(query);
+        }
+
+        let nl = self.whitespace.newline();
+
+        query.push(format!("{0}FROM {1}", nl, self.table));
+
+        for table in &self.from_tables {
+            query.push(format!(", {0}", table));
+        }
+
+        for (kind, table, on) in &self.joins {
+            query.push(format!("{0}{1} {2} {3}", nl, kind.as_sql(), table, on.as_sql()));
+        }
+
+        query
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{AggFunc, BaseQuery, Condition, ConditionBuilder, JoinKind, OrderedSetFunc, SampleMethod, SelectBuilder, SelectColumn, SelectError, SortDirection, WhitespaceStyle, WindowSpec, reorder, tablesample};
+    use sqlx::{Postgres, QueryBuilder};
+
+    #[test]
+    fn select_all_columns() {
+        let columns: Vec<&str> = Vec::new();
+
+        let select_query = SelectBuilder::new("sample_table", columns);
+        let result = "SELECT *\nFROM sample_table";
+
+        assert_eq!(select_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_two_columns() {
+        let columns: Vec<&str> = vec![
+            "column1",
+            "column2",
+        ];
+
+        let select_query = SelectBuilder::new("sample_table", columns);
+        let result = "SELECT column1, column2\nFROM sample_table";
+
+        assert_eq!(select_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_with_conditions() {
+        let columns: Vec<&str> = vec!["id", "name"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "org_id", "=", 5.into(), None)];
+
+        let select_query = SelectBuilder::new("users", columns);
+        let test_query = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(select_query.build()),
+            &conditions,
+            None,
+            Some(10),
+            None,
+            None,
+        );
+        let result = "SELECT id, name\nFROM users\nWHERE\n    org_id = $1\nLIMIT $2";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_distinct_on_reordered() {
+        let columns: Vec<&str> = vec!["customer_id", "total", "created_at"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "status", "=", "paid".into(), None)];
+
+        let select_query = SelectBuilder::new("orders", columns).distinct_on(vec!["customer_id"]);
+        let latest = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(select_query.build()),
+            &conditions,
+            Some("ORDER BY customer_id, created_at DESC"),
+            None,
+            None,
+            None,
+        );
+        let result = "SELECT *\nFROM (\nSELECT DISTINCT ON (customer_id) customer_id, total, created_at\nFROM orders\nWHERE\n    status = $1\nORDER BY customer_id, created_at DESC\n) sub\nORDER BY total DESC";
+
+        assert_eq!(reorder(latest.build(), "total DESC", WhitespaceStyle::Pretty).into_sql(), result);
+
+        let inner = QueryBuilder::<Postgres>::new("SELECT id, total FROM orders");
+
+        assert_eq!(
+            reorder(inner, "total DESC", WhitespaceStyle::SingleLine).into_sql(),
+            "SELECT * FROM ( SELECT id, total FROM orders ) sub ORDER BY total DESC"
+        );
+    }
+
+    #[test]
+    fn select_left_join() {
+        let columns: Vec<&str> = vec!["users.id", "orders.total"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "users.org_id", "=", 5.into(), None)];
+
+        let select_query = SelectBuilder::new("users", columns)
+            .join(JoinKind::Left, "orders", "orders.user_id = users.id");
+        let test_query = ConditionBuilder::new(
+            BaseQuery::QueryBuilder(select_query.build()),
+            &conditions,
+            None,
+            None,
+            None,
+            None,
+        );
+        let result = "SELECT users.id, orders.total\nFROM users\nLEFT JOIN orders ON orders.user_id = users.id\nWHERE\n    users.org_id = $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_chained_joins() {
+        let columns: Vec<&str> = Vec::new();
+
+        let select_query = SelectBuilder::new("orders", columns)
+            .join(JoinKind::Inner, "users", "users.id = orders.user_id")
+            .join(JoinKind::Full, "refunds", "refunds.order_id = orders.id");
+        let result = "SELECT *\nFROM orders\nINNER JOIN users ON users.id = orders.user_id\nFULL JOIN refunds ON refunds.order_id = orders.id";
+
+        assert_eq!(select_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_cross_schema_join() {
+        let columns: Vec<&str> = vec!["u.id", "r.name"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "u.active", "=", true.into(), None)];
+
+        let select_query = SelectBuilder::new("tenant_a.users u", columns)
+            .join(JoinKind::Inner, "shared.roles r", "u.role_id = r.id");
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(select_query.build()), &conditions, None, None, None, None);
+        let result = "SELECT u.id, r.name\nFROM tenant_a.users u\nINNER JOIN shared.roles r ON u.role_id = r.id\nWHERE\n    u.active = $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_join_using() {
+        let columns: Vec<&str> = vec!["user_id", "org_id", "role"];
+
+        let select_query = SelectBuilder::new("users", columns)
+            .join_using(JoinKind::Inner, "memberships", vec!["user_id", "org_id"])
+            .unwrap();
+        let result = "SELECT user_id, org_id, role\nFROM users\nINNER JOIN memberships USING (user_id, org_id)";
+
+        assert_eq!(select_query.build().into_sql(), result);
+        assert_eq!(
+            SelectBuilder::new("users", Vec::new()).join_using(JoinKind::Inner, "memberships", vec!["id) OR (1"]).err(),
+            Some(SelectError::InvalidIdentifier("id) OR (1".to_string()))
+        );
+    }
+
+    #[test]
+    fn select_comma_from() {
+        let columns: Vec<&str> = vec!["o.id", "c.name"];
+        let conditions: Vec<Condition> = vec![
+            Condition::from_expression(None, "o.customer_id", "=", crate::Expression::new().sql("o.customer_id = c.id")),
+            Condition::new(Some("AND"), "c.country", "=", "NL".into(), None),
+        ];
+
+        let select_query = SelectBuilder::new("orders o", columns).from_tables(vec!["customers c"]);
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(select_query.build()), &conditions, None, None, None, None);
+        let result = "SELECT o.id, c.name\nFROM orders o, customers c\nWHERE\n    o.customer_id = c.id\n    AND c.country = $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_sampled_join() {
+        let columns: Vec<&str> = vec!["u.id", "e.kind"];
+        let events = tablesample("events", Some("e"), SampleMethod::System, 1.0).unwrap();
+
+        let select_query = SelectBuilder::new("users u", columns).join(JoinKind::Inner, &events, "e.user_id = u.id");
+        let result = "SELECT u.id, e.kind\nFROM users u\nINNER JOIN events e TABLESAMPLE SYSTEM (1) ON e.user_id = u.id";
+
+        assert_eq!(select_query.build().into_sql(), result);
+        assert_eq!(
+            tablesample("events", None, SampleMethod::Bernoulli, f64::NAN).err().map(|err| err.to_string()),
+            Some("`NaN` is not a sample percent between 0 and 100".to_string())
+        );
+    }
+
+    #[test]
+    fn select_distinct() {
+        let columns: Vec<&str> = vec!["country", "city"];
+
+        let select_query = SelectBuilder::new("addresses", columns).distinct();
+        let result = "SELECT DISTINCT country, city\nFROM addresses";
+
+        assert_eq!(select_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_distinct_on_two_columns() {
+        let columns: Vec<&str> = vec!["customer_id", "product_id", "price"];
+
+        let select_query = SelectBuilder::new("orders", columns)
+            .distinct()
+            .distinct_on(vec!["customer_id", "product_id"]);
+        let result = "SELECT DISTINCT ON (customer_id, product_id) customer_id, product_id, price\nFROM orders";
+
+        assert_eq!(select_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_percentile() {
+        let columns: Vec<&str> = vec!["endpoint"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "day", "=", "2024-01-15".into(), None)];
+
+        let select_query = SelectBuilder::new("requests", columns)
+            .column(SelectColumn::OrderedSetAgg { func: OrderedSetFunc::PercentileCont(0.95), order_by: "latency", alias: "p95" })
+            .unwrap()
+            .column(SelectColumn::OrderedSetAgg { func: OrderedSetFunc::Mode, order_by: "status", alias: "common_status" })
+            .unwrap();
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(select_query.build()), &conditions, None, None, None, None)
+            .group_by(vec!["endpoint"]);
+        let result = "SELECT endpoint, percentile_cont(0.95) WITHIN GROUP (ORDER BY latency) AS p95, mode() WITHIN GROUP (ORDER BY status) AS common_status\nFROM requests\nWHERE\n    day = $1\nGROUP BY endpoint";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_invalid_fraction() {
+        for fraction in [1.5, -0.1, f64::NAN] {
+            let column = SelectColumn::OrderedSetAgg { func: OrderedSetFunc::PercentileDisc(fraction), order_by: "latency", alias: "p" };
+            let result = SelectBuilder::new("requests", Vec::new()).column(column);
+
+            assert!(matches!(result, Err(SelectError::InvalidFraction(_))));
+        }
+    }
+
+    #[test]
+    fn select_array_agg() {
+        let columns: Vec<&str> = vec!["team_id"];
+        let conditions: Vec<Condition> = Vec::new();
+
+        let select_query = SelectBuilder::new("members", columns)
+            .column(SelectColumn::Agg { func: AggFunc::ArrayAgg, arg: "name", order_by: Some("name"), distinct: false, separator: None, alias: Some("names") })
+            .unwrap();
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(select_query.build()), &conditions, None, None, None, None)
+            .group_by(vec!["team_id"]);
+        let result = "SELECT team_id, array_agg(name ORDER BY name) AS names\nFROM members\nGROUP BY team_id";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_string_agg() {
+        let columns: Vec<&str> = vec!["post_id"];
+        let conditions: Vec<Condition> = vec![Condition::new(None, "post_id", ">", 10.into(), None)];
+
+        let select_query = SelectBuilder::new("post_tags", columns)
+            .column(SelectColumn::Agg { func: AggFunc::StringAgg, arg: "tag", order_by: None, distinct: true, separator: Some(","), alias: None })
+            .unwrap();
+        let test_query = ConditionBuilder::new(BaseQuery::QueryBuilder(select_query.build()), &conditions, None, None, None, None)
+            .group_by(vec!["post_id"]);
+        let result = "SELECT post_id, string_agg(DISTINCT tag, ',')\nFROM post_tags\nWHERE\n    post_id > $1\nGROUP BY post_id";
+
+        assert_eq!(test_query.build().into_sql(), result);
+
+        let quoted = SelectBuilder::new("post_tags", Vec::new())
+            .column(SelectColumn::Agg { func: AggFunc::StringAgg, arg: "tag", order_by: None, distinct: false, separator: Some("', '"), alias: None })
+            .unwrap();
+
+        assert_eq!(quoted.build().into_sql(), "SELECT string_agg(tag, ''', ''')\nFROM post_tags");
+        assert!(matches!(
+            SelectBuilder::new("post_tags", Vec::new()).column(SelectColumn::Agg { func: AggFunc::StringAgg, arg: "tag", order_by: None, distinct: false, separator: None, alias: None }),
+            Err(SelectError::InvalidSeparator)
+        ));
+    }
+
+    #[test]
+    fn select_shared_window() {
+        let columns: Vec<&str> = vec!["id"];
+        let conditions: Vec<Condition> = vec![Condition::eq("org_id", 5)];
+
+        let select_query = SelectBuilder::new("payments", columns)
+            .column(SelectColumn::Window { func: "row_number()", window: "w", alias: Some("rn") })
+            .unwrap()
+            .column(SelectColumn::Window { func: "sum(amount)", window: "w", alias: Some("running") })
+            .unwrap()
+            .window("w", WindowSpec::new(vec!["customer_id"], vec![("created_at", SortDirection::Asc)]))
+            .unwrap();
+        let test_query = select_query
+            .with_conditions(&conditions)
+            .unwrap()
+            .order_by(vec![("id", SortDirection::Asc)]);
+        let result = "SELECT id, row_number() OVER w AS rn, sum(amount) OVER w AS running\nFROM payments\nWHERE\n    org_id = $1\nWINDOW w AS (PARTITION BY customer_id ORDER BY created_at ASC)\nORDER BY id ASC";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn select_unknown_window() {
+        let conditions: Vec<Condition> = Vec::new();
+
+        let select_query = SelectBuilder::new("payments", Vec::new())
+            .column(SelectColumn::Window { func: "rank()", window: "w", alias: None })
+            .unwrap();
+
+        assert_eq!(select_query.with_conditions(&conditions).err(), Some(SelectError::UnknownWindow("w".to_string())));
+    }
+}
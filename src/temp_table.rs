@@ -0,0 +1,257 @@
+use std::fmt;
+
+use serde_json::Value;
+use sqlx::{Postgres, QueryBuilder};
+
+use crate::{Condition, SqlValue, is_valid_identifier};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TempTableError {
+    /// table, column or target is not a plain identifier
+    InvalidIdentifier(String),
+    /// column type is not a type name ex.: `bigint`, `varchar(20)`, `text[]`
+    InvalidType(String),
+}
+
+impl fmt::Display for TempTableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TempTableError::InvalidIdentifier(name) => write!(f, "`{name}` is not a valid identifier"),
+            TempTableError::InvalidType(name) => write!(f, "`{name}` is not a valid type"),
+        }
+    }
+}
+
+impl std::error::Error for TempTableError {}
+
+/// replaces a huge `IN` list with a temporary table joined to the query
+/// statements must run in one transaction: `create_sql`, then `copy_sql` fed with `copy_data`
+/// (ex.: `PgConnection::copy_in_raw`), then the query joined by `join_sql`
+/// names and type are put in sql as is, so all of them return error if one is invalid
+#[derive(Debug, Clone)]
+pub struct TempTableIn<'a> {
+    /// temporary table name
+    pub table: &'a str,
+    /// column of temporary table
+    pub column: &'a str,
+    /// postgres type of temporary table column ex.: `bigint`
+    pub column_type: &'a str,
+    /// column of the query compared with the set ex.: `users.id`
+    pub target: &'a str,
+    pub values: Vec<Value>,
+}
+
+impl<'a> TempTableIn<'a> {
+    pub fn new(
+        table: &'a str,
+        column: &'a str,
+        column_type: &'a str,
+        target: &'a str,
+        values: Vec<Value>,
+    ) -> Self {
+        Self {
+            table,
+            column,
+            column_type,
+            target,
+            values,
+        }
+    }
+
+    /// takes values of an `IN` condition, None if condition is not `IN` with a list
+    pub fn from_condition(
+        table: &'a str,
+        column: &'a str,
+        column_type: &'a str,
+        condition: &Condition<'a>,
+    ) -> Option<Self> {
+        if !condition.eq_opr.eq_ignore_ascii_case("IN") || condition.expression.is_some() {
+            return None;
+        }
+
+        match &condition.value_l {
+            SqlValue::GenericValue(Value::Array(values)) => Some(Self::new(
+                table,
+                column,
+                column_type,
+                condition.column,
+                values.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// table is dropped at commit, so outside of a transaction it is gone before `copy_sql`,
+    /// run it after `BEGIN` or push it as first statement of a `Batch` with isolation level
+    pub fn create_sql(&self) -> Result<QueryBuilder<'a, Postgres>, TempTableError> {
+        self.validate()?;
+
+        Ok(QueryBuilder::new(format!(
+            "CREATE TEMP TABLE {0} ({1} {2}) ON COMMIT DROP",
+            self.table, self.column, self.column_type
+        )))
+    }
+
+    pub fn copy_sql(&self) -> Result<String, TempTableError> {
+        self.validate()?;
+
+        Ok(format!("COPY {0} ({1}) FROM STDIN", self.table, self.column))
+    }
+
+    /// values in COPY text format, one per line
+    pub fn copy_data(&self) -> String {
+        let mut data = String::new();
+
+        for value in self.values.iter() {
+            match value {
+                Value::Null => data.push_str("\\N"),
+                Value::String(v) => data.push_str(&Self::escape_copy(v)),
+                v => data.push_str(&Self::escape_copy(&v.to_string())),
+            }
+
+            data.push('\n');
+        }
+
+        data
+    }
+
+    /// join replacing the `IN` condition, values are distinct so repeated ones do not duplicate rows
+    /// ex.: `JOIN (SELECT DISTINCT id FROM ids) ids ON users.id = ids.id`
+    pub fn join_sql(&self) -> Result<String, TempTableError> {
+        self.validate()?;
+
+        Ok(format!(
+            "JOIN (SELECT DISTINCT {2} FROM {0}) {0} ON {1} = {0}.{2}",
+            self.table, self.target, self.column
+        ))
+    }
+
+    /// table and column must be unqualified as they are used as alias and column of the join
+    fn validate(&self) -> Result<(), TempTableError> {
+        for name in [self.table, self.column] {
+            if name.contains('.') || !is_valid_identifier(name) {
+                return Err(TempTableError::InvalidIdentifier(name.to_string()));
+            }
+        }
+
+        if !is_valid_identifier(self.target) {
+            return Err(TempTableError::InvalidIdentifier(self.target.to_string()));
+        }
+
+        if !Self::is_valid_type(self.column_type) {
+            return Err(TempTableError::InvalidType(self.column_type.to_string()));
+        }
+
+        Ok(())
+    }
+
+    /// words of type name with optional modifiers and array brackets ex.: `timestamp with time zone`, `numeric(10, 2)[]`
+    fn is_valid_type(column_type: &str) -> bool {
+        let mut name = column_type.trim_end();
+
+        while let Some(element) = name.strip_suffix("[]") {
+            name = element.trim_end();
+        }
+
+        if let Some(open) = name.find('(') {
+            let modifiers = match name[open + 1..].strip_suffix(')') {
+                Some(modifiers) => modifiers,
+                None => return false,
+            };
+
+            if !modifiers.split(',').all(|modifier| !modifier.trim().is_empty() && modifier.trim().chars().all(|c| c.is_ascii_digit())) {
+                return false;
+            }
+
+            name = name[..open].trim_end();
+        }
+
+        !name.is_empty() && name.split_whitespace().all(is_valid_identifier)
+    }
+
+    fn escape_copy(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{BaseQuery, Batch, Condition, ConditionBuilder, IsolationLevel, TempTableError, TempTableIn};
+
+    #[test]
+    fn temp_table_join_rewrite() {
+        let ids: Vec<i64> = vec![3, 5, 8];
+        let in_condition = Condition::new(None, "users.id", "IN", ids.into(), None);
+        let plan = TempTableIn::from_condition("ids", "id", "bigint", &in_condition).unwrap();
+
+        assert_eq!(plan.create_sql().unwrap().into_sql(), "CREATE TEMP TABLE ids (id bigint) ON COMMIT DROP");
+        assert_eq!(plan.copy_sql().unwrap(), "COPY ids (id) FROM STDIN");
+        assert_eq!(plan.copy_data(), "3\n5\n8\n");
+
+        let base_query = format!("SELECT users.*\nFROM users\n{0}", plan.join_sql().unwrap());
+        let conditions: Vec<Condition> = vec![Condition::new(None, "users.active", "=", true.into(), None)];
+        let test_query =
+            ConditionBuilder::new(BaseQuery::Sql(&base_query), &conditions, None, None, None, None);
+
+        let result = "SELECT users.*\nFROM users\nJOIN (SELECT DISTINCT id FROM ids) ids ON users.id = ids.id\nWHERE\n    users.active = $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn temp_table_repeated_values() {
+        let ids: Vec<i64> = vec![3, 3, 5];
+        let in_condition = Condition::new(None, "orders.user_id", "IN", ids.into(), None);
+        let plan = TempTableIn::from_condition("ids", "id", "bigint", &in_condition).unwrap();
+
+        assert_eq!(plan.copy_data(), "3\n3\n5\n");
+        assert_eq!(plan.join_sql().unwrap(), "JOIN (SELECT DISTINCT id FROM ids) ids ON orders.user_id = ids.id");
+    }
+
+    #[test]
+    fn temp_table_in_batch() {
+        let plan = TempTableIn::new("ids", "id", "numeric(10, 2)[]", "users.id", Vec::new());
+        let batch = Batch::new()
+            .isolation(IsolationLevel::ReadCommitted)
+            .push(plan.create_sql().unwrap());
+
+        let statements: Vec<String> = batch.into_statements().into_iter().map(|statement| statement.into_sql()).collect();
+
+        assert_eq!(statements, vec![
+            "BEGIN ISOLATION LEVEL READ COMMITTED",
+            "CREATE TEMP TABLE ids (id numeric(10, 2)[]) ON COMMIT DROP",
+            "COMMIT",
+        ]);
+    }
+
+    #[test]
+    fn temp_table_invalid_names() {
+        let invalid_table = TempTableIn::new("ids; DROP TABLE users", "id", "bigint", "users.id", Vec::new());
+        let qualified_column = TempTableIn::new("ids", "ids.id", "bigint", "users.id", Vec::new());
+        let invalid_target = TempTableIn::new("ids", "id", "bigint", "users.id OR TRUE", Vec::new());
+        let invalid_type = TempTableIn::new("ids", "id", "bigint) ON COMMIT DROP; --", "users.id", Vec::new());
+
+        assert_eq!(invalid_table.create_sql().err(), Some(TempTableError::InvalidIdentifier("ids; DROP TABLE users".to_string())));
+        assert_eq!(qualified_column.copy_sql(), Err(TempTableError::InvalidIdentifier("ids.id".to_string())));
+        assert_eq!(invalid_target.join_sql(), Err(TempTableError::InvalidIdentifier("users.id OR TRUE".to_string())));
+        assert_eq!(invalid_type.create_sql().err(), Some(TempTableError::InvalidType("bigint) ON COMMIT DROP; --".to_string())));
+        assert!(TempTableIn::new("ids", "id", "timestamp with time zone", "t.at", Vec::new()).create_sql().is_ok());
+    }
+
+    #[test]
+    fn temp_table_copy_data_escaped() {
+        let codes: Vec<&str> = vec!["a\tb", "c\\d"];
+        let eq_condition = Condition::new(None, "code", "=", codes.clone().into(), None);
+
+        assert!(TempTableIn::from_condition("codes", "code", "text", &eq_condition).is_none());
+
+        let in_condition = Condition::new(None, "code", "in", codes.into(), None);
+        let plan = TempTableIn::from_condition("codes", "code", "text", &in_condition).unwrap();
+
+        assert_eq!(plan.copy_data(), "a\\tb\nc\\\\d\n");
+    }
+}
@@ -1,15 +1,63 @@
+use std::collections::BTreeMap;
+
+use serde_json::Value;
 use sqlx::{Postgres, QueryBuilder};
 
-use crate::{BaseQuery, Condition, ConditionBuilder, SqlValue, push_sqlvalue};
+use crate::{BaseQuery, Condition, ConditionBuilder, Expression, InsertError, Row, SqlValue, WhitespaceStyle, bound_values, preview_sql, push_expression, push_sqlvalue};
+
+/// value of updated column
+#[derive(Debug, Clone)]
+pub enum UpdColumnType {
+    Value(SqlValue),
+    /// ex.: `SET meta = jsonb_set(meta, '{a}', $1::jsonb)`
+    Expression(Expression),
+    /// rendered in parentheses, values bound in place ex.: `SET order_count = (SELECT count(*) FROM orders WHERE user_id = users.id)`,
+    /// a query of `ConditionBuilder` is taken by `ConditionBuilder::into_expression`
+    Subquery(Expression),
+    /// resets column to its declared default ex.: `SET status = DEFAULT`
+    Default,
+}
+
+impl UpdColumnType {
+    /// values bound when the column is rendered, in order
+    pub fn bound_values(&self) -> Vec<SqlValue> {
+        match self {
+            UpdColumnType::Value(value) => bound_values(value),
+            UpdColumnType::Expression(expression) | UpdColumnType::Subquery(expression) => expression.bound_values(),
+            UpdColumnType::Default => Vec::new(),
+        }
+    }
+}
+
+impl<T: Into<SqlValue>> From<T> for UpdColumnType {
+    fn from(value: T) -> Self {
+        Self::Value(value.into())
+    }
+}
+
+impl From<Expression> for UpdColumnType {
+    fn from(value: Expression) -> Self {
+        Self::Expression(value)
+    }
+}
 
 pub type Column<'a> = (&'a str, SqlValue);
 
+/// updated column with value, expression, subquery or DEFAULT, see `UpdColumnType`
+pub type UpdColumn<'a> = (&'a str, UpdColumnType);
+
 #[derive(Debug)]
 pub struct UpdateBuilder<'a> {
     pub table: &'a str,
     pub columns: Vec<Column<'a>>,
+    /// columns set by expression, subquery or DEFAULT, rendered after `columns`, see `set_columns`
+    pub upd_columns: Vec<UpdColumn<'a>>,
     pub conditions: Vec<Condition<'a>>,
     pub end: Option<&'a str>,
+    /// rendered as trailing comment by `build_all`, see `tag_comment`
+    pub tag: Option<BTreeMap<&'a str, &'a str>>,
+    /// line layout of built query, see `WhitespaceStyle`
+    pub whitespace: WhitespaceStyle,
 }
 
 impl<'a> UpdateBuilder<'a> {
@@ -26,31 +74,65 @@ impl<'a> UpdateBuilder<'a> {
         Self {
             table,
             columns,
+            upd_columns: Vec::new(),
             conditions,
             end,
+            tag: None,
+            whitespace: WhitespaceStyle::default(),
         }
     }
 
+    /// columns set by expression, subquery or DEFAULT ex.: `SET meta = jsonb_set(meta, '{a}', $1::jsonb)`
+    pub fn set_columns(mut self, columns: Vec<UpdColumn<'a>>) -> Self {
+        self.upd_columns = columns;
+        self
+    }
+
+    pub fn tag(mut self, tag: BTreeMap<&'a str, &'a str>) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// value columns first, then `upd_columns`
+    fn set_list(&self) -> Vec<UpdColumn<'a>> {
+        self.columns
+            .iter()
+            .map(|(column, value)| (*column, UpdColumnType::Value(value.clone())))
+            .chain(self.upd_columns.iter().cloned())
+            .collect()
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
     pub fn build(&self) -> QueryBuilder<'_, Postgres> {
-        let mut query: QueryBuilder<'_, Postgres> = QueryBuilder::new("");
+        self.build_after(QueryBuilder::new(""))
+    }
 
-        if !self.columns.is_empty() {
+    /// same as build, but continues query ex.: `WithBuilder::build_prefix`, values of query are numbered first
+    pub fn build_after<'q>(&'q self, mut query: QueryBuilder<'q, Postgres>) -> QueryBuilder<'q, Postgres> {
+        let indent = self.whitespace.indent();
+        let columns = self.set_list();
+
+        if !columns.is_empty() {
             let base_query = format!("UPDATE {}", self.table);
             query.push(base_query);
 
-            for (index, column) in self.columns.iter().enumerate() {
+            for (index, column) in columns.iter().enumerate() {
                 if index == 0 {
-                    query.push(format!("\n    SET {0} = ", column.0));
-                    query = push_sqlvalue(column.1.clone(), query);
+                    query.push(format!("{0}SET {1} = ", indent, column.0));
+                    query = push_updcolumn(column.1.clone(), query);
 
-                    if index < self.columns.len() - 1 {
+                    if index < columns.len() - 1 {
                         query.push(",");
                     }
                 } else {
-                    query.push(format!("\n    {0} = ", column.0));
-                    query = push_sqlvalue(column.1.clone(), query);
+                    query.push(format!("{0}{1} = ", indent, column.0));
+                    query = push_updcolumn(column.1.clone(), query);
 
-                    if index < self.columns.len() - 1 {
+                    if index < columns.len() - 1 {
                         query.push(",");
                     }
                 }
@@ -60,28 +142,193 @@ impl<'a> UpdateBuilder<'a> {
         query
     }
 
+    /// DEBUGGING AID ONLY, `build_all` with bound values inlined as literals, see `preview_sql`
+    pub fn preview_sql(&mut self) -> String {
+        let mut values: Vec<SqlValue> = self.set_list().iter().flat_map(|(_, value)| value.bound_values()).collect();
+        values.extend(ConditionBuilder::new(BaseQuery::Sql(""), &self.conditions, None, None, None, None).bound_values());
+
+        preview_sql(&self.build_all().into_sql(), 0, &values)
+    }
+
     pub fn build_all(&mut self) -> QueryBuilder<'_, Postgres> {
-        let query: QueryBuilder<'_, Postgres> = self.build();
+        self.build_all_after(QueryBuilder::new(""))
+    }
+
+    /// same as build_all, but continues query ex.: `WithBuilder::build_prefix`, values of query are numbered first
+    pub fn build_all_after<'q>(&'q mut self, query: QueryBuilder<'q, Postgres>) -> QueryBuilder<'q, Postgres> {
+        let query: QueryBuilder<'_, Postgres> = self.build_after(query);
 
-        let query_new = ConditionBuilder::new(
+        let mut condition_builder = ConditionBuilder::new(
             BaseQuery::QueryBuilder(query),
             &self.conditions,
             None,
             None,
             None,
             self.end,
-        )
-        .build();
+        );
+        condition_builder.tag = self.tag.clone();
+        condition_builder.whitespace = self.whitespace;
+
+        condition_builder.build()
+    }
+}
+
+/// updates many rows with different values in one query, columns are set from a VALUES list joined by key
+/// ex.: `UPDATE t SET x = v.x FROM (VALUES ($1::bigint, $2::text)) v(id, x) WHERE t.id = v.id`
+pub struct BulkUpdateBuilder<'a> {
+    pub table: &'a str,
+    /// key column and its type, rows are matched by it
+    pub key: (&'a str, &'a str),
+    /// updated columns and their types, types are cast in first row only as postgres infers the rest from it
+    pub columns: &'a Vec<(&'a str, &'a str)>,
+    /// key value first, then values of columns in order, None is NULL
+    pub rows: &'a Vec<Row>,
+    /// columns qualified by table ex.: `RETURNING t.id, t.x`
+    pub returning: Vec<&'a str>,
+    pub whitespace: WhitespaceStyle,
+}
+
+impl<'a> BulkUpdateBuilder<'a> {
+    pub fn new(
+        table: &'a str,
+        key: (&'a str, &'a str),
+        columns: &'a Vec<(&'a str, &'a str)>,
+        rows: &'a Vec<Row>,
+    ) -> Self {
+        Self {
+            table,
+            key,
+            columns,
+            rows,
+            returning: Vec::new(),
+            whitespace: WhitespaceStyle::default(),
+        }
+    }
+
+    pub fn returning(mut self, columns: Vec<&'a str>) -> Self {
+        self.returning = columns;
+        self
+    }
+
+    pub fn whitespace(mut self, style: WhitespaceStyle) -> Self {
+        self.whitespace = style;
+        self
+    }
+
+    /// empty query if there is no row or column, error for first row with other length than key and columns
+    pub fn build(&self) -> Result<QueryBuilder<'a, Postgres>, InsertError> {
+        let mut query: QueryBuilder<'a, Postgres> = QueryBuilder::new("");
+        let expected = self.columns.len() + 1;
+
+        if let Some((row_index, row)) = self.rows.iter().enumerate().find(|(_, row)| row.len() != expected) {
+            return Err(InsertError::RowArity { row_index, expected, got: row.len() });
+        }
+
+        let rows: Vec<&Row> = self.rows.iter().collect();
+
+        if rows.is_empty() || self.columns.is_empty() {
+            return Ok(query);
+        }
+
+        let nl = self.whitespace.newline();
+        let indent = self.whitespace.indent();
+        let set: Vec<String> = self.columns.iter().map(|(column, _)| format!("{0} = v.{0}", column)).collect();
+        query.push(format!("UPDATE {0}{1}SET {2}{3}FROM ({3}VALUES", self.table, indent, set.join(&format!(",{0}", indent)), nl));
+
+        let types: Vec<&str> = std::iter::once(self.key.1).chain(self.columns.iter().map(|(_, cast)| *cast)).collect();
+
+        for (row_index, row) in rows.iter().enumerate() {
+            if row_index > 0 {
+                query.push(",");
+            }
+
+            query.push(format!("{0}(", self.whitespace.row_indent()));
+
+            for (index, value) in row.iter().enumerate() {
+                if index > 0 {
+                    query.push(", ");
+                }
+
+                match value {
+                    Some(value) => query = push_sqlvalue(value.clone(), query),
+                    None => { query.push("NULL"); },
+                }
+
+                if row_index == 0 && !types[index].is_empty() {
+                    query.push(format!("::{0}", types[index]));
+                }
+            }
+
+            query.push(")");
+        }
 
-        query_new
+        let names: Vec<&str> = std::iter::once(self.key.0).chain(self.columns.iter().map(|(column, _)| *column)).collect();
+        query.push(format!("{0}) v({1}){0}WHERE {2}.{3} = v.{3}", nl, names.join(", "), self.table, self.key.0));
+
+        if !self.returning.is_empty() {
+            let returning: Vec<String> = self.returning.iter().map(|column| format!("{0}.{1}", self.table, column)).collect();
+            query.push(format!("{0}RETURNING {1}", nl, returning.join(", ")));
+        }
+
+        Ok(query)
+    }
+}
+
+pub fn push_updcolumn(value: UpdColumnType, mut query_builder: QueryBuilder<'_, Postgres>) -> QueryBuilder<'_, Postgres> {
+    match value {
+        UpdColumnType::Value(v) => push_sqlvalue(v, query_builder),
+        UpdColumnType::Expression(expression) => push_expression(expression, query_builder),
+        UpdColumnType::Subquery(subquery) => {
+            let subquery = Expression::new().sql("(").expression(subquery).sql(")");
+
+            push_expression(subquery, query_builder)
+        },
+        UpdColumnType::Default => {
+            query_builder.push("DEFAULT");
+            query_builder
+        },
+    }
+}
+
+/// updates only given keys of jsonb column instead of overwriting whole document
+/// ex.: `jsonb_set(jsonb_set(meta, '{a}', $1::jsonb), '{b}', $2::jsonb)`
+pub fn jsonb_set_keys(column: &str, changes: Vec<(&str, Value)>) -> Expression {
+    let mut expression = Expression::new().sql(column);
+
+    for (key, value) in changes {
+        expression = Expression::new()
+            .sql("jsonb_set(")
+            .expression(expression)
+            .sql(format!(", '{{{0}}}', ", jsonb_path_key(key)))
+            .value(value.to_string())
+            .sql("::jsonb)");
     }
+
+    expression
+}
+
+/// key as element of text array literal, quoted if it is not a plain word
+fn jsonb_path_key(key: &str) -> String {
+    if !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return key.to_string();
+    }
+
+    let escaped = key.replace('\\', "\\\\").replace('"', "\\\"").replace('\'', "''");
+
+    format!("\"{escaped}\"")
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use chrono::Utc;
 
-    use crate::{Column, Condition, UpdateBuilder};
+    use serde_json::json;
+
+    use sqlx::{Postgres, QueryBuilder};
+
+    use crate::{AggFunc, BaseQuery, BulkUpdateBuilder, Column, Condition, ConditionBuilder, ConditionError, Expression, InsertError, Row, SelectBuilder, SelectColumn, UpdColumn, UpdColumnType, UpdateBuilder, WhitespaceStyle, jsonb_set_keys};
 
     #[test]
     fn update_datetime() {
@@ -132,8 +379,7 @@ mod tests {
             ("col3", 7.into()),
         ];
 
-        let mut conditions: Vec<Condition> = Vec::new();
-        conditions.push(Condition::new(None, "id", "=", 5.into(), None));
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
         let mut test_query = UpdateBuilder::new("sample_table", columns, conditions, None);
         let result = "UPDATE sample_table\n    SET col1 = $1,\n    col2 = $2,\n    col3 = $3\nWHERE\n    id = $4";
 
@@ -148,8 +394,7 @@ mod tests {
             ("col3", 7.into()),
         ];
 
-        let mut conditions: Vec<Condition> = Vec::new();
-        conditions.push(Condition::new(None, "id", "=", 5.into(), None));
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
 
         let mut test_query =
             UpdateBuilder::new("sample_table", columns, conditions, Some("RETURNING id"));
@@ -157,4 +402,203 @@ mod tests {
 
         assert_eq!(test_query.build_all().into_sql(), result);
     }
+
+    #[test]
+    fn update_with_tag() {
+        let columns: Vec<Column> = vec![("col1", 5.into())];
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+
+        let mut test_query = UpdateBuilder::new("sample_table", columns, conditions, None)
+            .tag(BTreeMap::from([("app", "svc")]));
+        let result = "UPDATE sample_table\n    SET col1 = $1\nWHERE\n    id = $2\n/* app=svc */";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn update_optional_values() {
+        let deleted_at: Option<&str> = None;
+        let score: Option<i32> = Some(7);
+
+        let columns: Vec<Column> = vec![("deleted_at", deleted_at.into()), ("score", (&score).into())];
+
+        let conditions: Vec<Condition> = Vec::new();
+        let test_query = UpdateBuilder::new("sample_table", columns, conditions, None);
+        let result = "UPDATE sample_table\n    SET deleted_at = NULL,\n    score = $1";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn update_jsonb_keys() {
+        let changes = vec![("a", json!(1)), ("b", json!({"c": true}))];
+        let columns: Vec<UpdColumn> = vec![("meta", jsonb_set_keys("meta", changes).into())];
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+
+        let mut test_query = UpdateBuilder::new("sample_table", Vec::new(), conditions, None).set_columns(columns);
+        let result = "UPDATE sample_table\n    SET meta = jsonb_set(jsonb_set(meta, '{a}', $1::jsonb), '{b}', $2::jsonb)\nWHERE\n    id = $3";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn update_jsonb_quoted_key() {
+        let columns: Vec<UpdColumn> = vec![("meta", jsonb_set_keys("meta", vec![("it's a,b", json!(1))]).into())];
+
+        let test_query = UpdateBuilder::new("sample_table", Vec::new(), Vec::new(), None).set_columns(columns);
+        let result = "UPDATE sample_table\n    SET meta = jsonb_set(meta, '{\"it''s a,b\"}', $1::jsonb)";
+
+        assert_eq!(test_query.build().into_sql(), result);
+    }
+
+    #[test]
+    fn update_with_subquery() {
+        let subquery_conditions: Vec<Condition> = vec![
+            Condition::from_expression(None, "orders.user_id", "=", Expression::new().sql("orders.user_id = users.id")),
+            Condition::new(Some("AND"), "orders.status", "IN", vec!["paid", "shipped"].into(), None),
+        ];
+        let subquery = ConditionBuilder::new(
+            BaseQuery::Sql("SELECT count(*)\nFROM orders"),
+            &subquery_conditions,
+            None,
+            None,
+            None,
+            None,
+        )
+        .into_expression()
+        .unwrap();
+
+        let columns: Vec<Column> = vec![("synced", true.into())];
+        let upd_columns: Vec<UpdColumn> = vec![("order_count", UpdColumnType::Subquery(subquery))];
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+
+        let mut test_query = UpdateBuilder::new("users", columns, conditions, None).set_columns(upd_columns);
+        let result = "UPDATE users\n    SET synced = $1,\n    order_count = (SELECT count(*)\nFROM orders\nWHERE\n    orders.user_id = users.id\n    AND orders.status IN ($2, $3))\nWHERE\n    id = $4";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn update_with_subquery_placeholder_in_literal() {
+        let subquery_conditions: Vec<Condition> = vec![
+            Condition::from_expression(None, "orders.user_id", "=", Expression::new().sql("orders.user_id = users.id")),
+            Condition::and_eq("orders.status", "paid"),
+        ];
+        let subquery = SelectBuilder::new("orders", Vec::new())
+            .column(SelectColumn::Agg { func: AggFunc::StringAgg, arg: "note", order_by: None, distinct: false, separator: Some("$1"), alias: None })
+            .unwrap()
+            .with_conditions(&subquery_conditions)
+            .unwrap()
+            .tag(BTreeMap::from([("job", "$2")]))
+            .into_expression()
+            .unwrap();
+
+        let columns: Vec<Column> = vec![("synced", true.into())];
+        let upd_columns: Vec<UpdColumn> = vec![("notes", UpdColumnType::Subquery(subquery))];
+        let conditions: Vec<Condition> = vec![Condition::eq("id", 5)];
+
+        let mut test_query = UpdateBuilder::new("users", columns, conditions, None).set_columns(upd_columns);
+        let result = "UPDATE users\n    SET synced = $1,\n    notes = (SELECT string_agg(note, '$1')\nFROM orders\nWHERE\n    orders.user_id = users.id\n    AND orders.status = $2\n/* job=$2 */)\nWHERE\n    id = $3";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn update_with_bound_base_subquery() {
+        let mut base: QueryBuilder<Postgres> = QueryBuilder::new("SELECT count(*)\nFROM orders\nWHERE status = ");
+        base.push_bind("paid");
+        let conditions: Vec<Condition> = Vec::new();
+
+        let subquery = ConditionBuilder::new(BaseQuery::QueryBuilder(base), &conditions, None, None, None, None).into_expression();
+
+        assert!(matches!(subquery, Err(ConditionError::BoundBaseQuery)));
+    }
+
+    #[test]
+    fn update_with_default() {
+        let columns: Vec<Column> = vec![("note", "reset".into())];
+
+        let conditions: Vec<Condition> = vec![Condition::new(None, "id", "=", 5.into(), None)];
+
+        let mut test_query = UpdateBuilder::new("users", columns, conditions, None).set_columns(vec![("status", UpdColumnType::Default)]);
+        let result = "UPDATE users\n    SET note = $1,\n    status = DEFAULT\nWHERE\n    id = $2";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn bulk_update_with_returning() {
+        let columns: Vec<(&str, &str)> = vec![("price", "numeric"), ("note", "text")];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some(json!(9.5).into()), Some("sale".into())],
+            vec![Some(2.into()), Some(12.into()), None],
+        ];
+
+        let test_query = BulkUpdateBuilder::new("products", ("id", "bigint"), &columns, &rows)
+            .returning(vec!["id", "price"]);
+        let result = "UPDATE products\n    SET price = v.price,\n    note = v.note\nFROM (\nVALUES\n       ($1::bigint, $2::numeric, $3::text),\n       ($4, $5, NULL)\n) v(id, price, note)\nWHERE products.id = v.id\nRETURNING products.id, products.price";
+
+        assert_eq!(test_query.build().unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn bulk_update_single_line() {
+        let columns: Vec<(&str, &str)> = vec![("price", "numeric"), ("note", "text")];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some(json!(9.5).into()), Some("sale".into())],
+            vec![Some(2.into()), Some(12.into()), None],
+        ];
+
+        let test_query = BulkUpdateBuilder::new("products", ("id", "bigint"), &columns, &rows)
+            .returning(vec!["id"])
+            .whitespace(WhitespaceStyle::SingleLine);
+        let result = "UPDATE products SET price = v.price, note = v.note FROM ( VALUES ($1::bigint, $2::numeric, $3::text), ($4, $5, NULL) ) v(id, price, note) WHERE products.id = v.id RETURNING products.id";
+
+        assert_eq!(test_query.build().unwrap().into_sql(), result);
+    }
+
+    #[test]
+    fn bulk_update_row_arity() {
+        let columns: Vec<(&str, &str)> = vec![("price", "numeric"), ("note", "text")];
+        let rows: Vec<Row> = vec![
+            vec![Some(1.into()), Some(json!(9.5).into()), Some("sale".into())],
+            vec![Some(3.into())],
+        ];
+
+        let test_query = BulkUpdateBuilder::new("products", ("id", "bigint"), &columns, &rows);
+
+        assert_eq!(test_query.build().err(), Some(InsertError::RowArity { row_index: 1, expected: 3, got: 1 }));
+    }
+
+    #[test]
+    fn update_compact() {
+        let columns: Vec<Column> = vec![("col1", 5.into()), ("col2", 3.into())];
+        let conditions: Vec<Condition> = vec![Condition::eq("id", 5)];
+
+        let mut test_query = UpdateBuilder::new("sample_table", columns, conditions, None).whitespace(WhitespaceStyle::Compact);
+        let result = "UPDATE sample_table\nSET col1 = $1,\ncol2 = $2\nWHERE\nid = $3";
+
+        assert_eq!(test_query.build_all().into_sql(), result);
+    }
+
+    #[test]
+    fn update_preview() {
+        let columns: Vec<Column> = vec![("name", "O'Brien".into())];
+        let upd_columns: Vec<UpdColumn> = vec![
+            ("visits", Expression::new().sql("visits + ").value(1).into()),
+            ("note", UpdColumnType::Default),
+        ];
+        let conditions: Vec<Condition> = vec![
+            Condition::eq("id", 5),
+            Condition::new(Some("AND"), "status", "IN", vec!["new", "paid"].into(), None),
+        ];
+
+        let mut test_query = UpdateBuilder::new("users", columns, conditions, Some("RETURNING id")).set_columns(upd_columns);
+        let result = "UPDATE users\n    SET name = 'O''Brien',\n    visits = visits + 1,\n    note = DEFAULT\nWHERE\n    id = 5\n    AND status IN ('new', 'paid')\nRETURNING id";
+
+        assert_eq!(test_query.preview_sql(), result);
+    }
 }